@@ -0,0 +1,18 @@
+// Copyright (c) 2023 Xu Shaohua <shaohua@biofan.org>. All rights reserved.
+// Use of this source is governed by General Public License that can be found
+// in the LICENSE file.
+
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+/// Copy C string returned by libc or libkmod, null pointer is converted to empty string.
+///
+/// # Safety
+/// `ptr` shall be null or a valid C string.
+pub unsafe fn from_c_str(ptr: *const c_char) -> String {
+    if ptr.is_null() {
+        String::new()
+    } else {
+        CStr::from_ptr(ptr).to_string_lossy().into_owned()
+    }
+}
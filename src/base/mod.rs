@@ -2,4 +2,9 @@
 // Use of this source is governed by General Public License that can be found
 // in the LICENSE file.
 
+pub(crate) mod c_str;
+pub(crate) mod nss;
+pub mod parse_mode;
+pub mod system_root;
 pub mod unit;
+pub(crate) mod utmp;
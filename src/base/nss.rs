@@ -0,0 +1,15 @@
+// Copyright (c) 2023 Xu Shaohua <shaohua@biofan.org>. All rights reserved.
+// Use of this source is governed by General Public License that can be found
+// in the LICENSE file.
+
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use once_cell::sync::Lazy;
+
+/// `getgrent()` and `getpwent()` keep position and returned entry in global state.
+static NSS_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// Serialize NSS enumeration in this process until returned guard is dropped.
+pub fn lock() -> MutexGuard<'static, ()> {
+    NSS_LOCK.lock().unwrap_or_else(PoisonError::into_inner)
+}
@@ -0,0 +1,73 @@
+// Copyright (c) 2023 Xu Shaohua <shaohua@biofan.org>. All rights reserved.
+// Use of this source is governed by General Public License that can be found
+// in the LICENSE file.
+
+use crate::error::Error;
+
+/// How collectors handle records which failed to parse.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum ParseMode {
+    /// Abort on first error.
+    #[default]
+    Strict,
+
+    /// Skip bad records and keep their errors as warnings.
+    Lenient,
+}
+
+/// Parsed value, along with errors skipped in lenient mode.
+#[derive(Debug, Default)]
+pub struct Parsed<T> {
+    pub value: T,
+    pub warnings: Vec<Error>,
+}
+
+impl ParseMode {
+    /// Check result of parsing a record.
+    ///
+    /// Returns error as is in strict mode. In lenient mode, error is appended
+    /// to `warnings` and `None` is returned so that the record can be skipped.
+    ///
+    /// # Errors
+    /// Returns error of `result` in strict mode.
+    pub fn check<T>(
+        self,
+        result: Result<T, Error>,
+        warnings: &mut Vec<Error>,
+    ) -> Result<Option<T>, Error> {
+        match (result, self) {
+            (Ok(value), _) => Ok(Some(value)),
+            (Err(err), Self::Strict) => Err(err),
+            (Err(err), Self::Lenient) => {
+                log::warn!("Skip record: {err}");
+                warnings.push(err);
+                Ok(None)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ParseMode;
+    use crate::error::Error;
+
+    #[test]
+    fn test_check() {
+        let mut warnings = Vec::new();
+        let err = || Err::<i32, _>(Error::malformed("/proc/net/dev", Some(3), "mtu", "x"));
+
+        assert_eq!(
+            ParseMode::Strict.check(Ok(1), &mut warnings).unwrap(),
+            Some(1)
+        );
+        assert!(ParseMode::Strict.check(err(), &mut warnings).is_err());
+        assert!(warnings.is_empty());
+
+        assert_eq!(
+            ParseMode::Lenient.check(err(), &mut warnings).unwrap(),
+            None
+        );
+        assert_eq!(warnings.len(), 1);
+    }
+}
@@ -0,0 +1,188 @@
+// Copyright (c) 2023 Xu Shaohua <shaohua@biofan.org>. All rights reserved.
+// Use of this source is governed by General Public License that can be found
+// in the LICENSE file.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::Error;
+use crate::snapshot::{self, Recorder};
+
+/// Root directory of the system to collect info from.
+///
+/// Every collector reads procfs, sysfs and config files through this object,
+/// instead of hardcoded absolute paths. Default root is `/`, the running system.
+/// Point it to a chroot, a mounted container rootfs or a fixture directory
+/// to read from there instead.
+#[derive(Debug, Clone)]
+pub struct SystemRoot {
+    root: PathBuf,
+    recorder: Option<Recorder>,
+}
+
+impl Default for SystemRoot {
+    fn default() -> Self {
+        Self::new("/")
+    }
+}
+
+impl SystemRoot {
+    #[must_use]
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self {
+            root: root.into(),
+            recorder: None,
+        }
+    }
+
+    /// Record every file and directory read through this root to `recorder`.
+    #[must_use]
+    pub fn with_recorder(mut self, recorder: Recorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    #[must_use]
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Map absolute `path` in target system to path in local filesystem.
+    ///
+    /// e.g. `/proc/modules` is mapped to `/mnt/rootfs/proc/modules`.
+    #[must_use]
+    pub fn join<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+        let path = path.as_ref();
+        let relative = path.strip_prefix("/").unwrap_or(path);
+        self.root.join(relative)
+    }
+
+    #[must_use]
+    pub fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.join(path).exists()
+    }
+
+    #[must_use]
+    pub fn is_dir<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.join(path).is_dir()
+    }
+
+    /// Whether root is an unpacked snapshot instead of a live system.
+    ///
+    /// Only file content is kept in snapshot, metadata like access time is not.
+    #[must_use]
+    pub fn is_snapshot(&self) -> bool {
+        self.exists(snapshot::MARKER_FILE)
+    }
+
+    /// Whether root is the running system, so that syscalls and environment of
+    /// current process describe it too.
+    #[must_use]
+    pub fn is_live(&self) -> bool {
+        self.root == Path::new("/")
+    }
+
+    /// Get metadata of file at `path` in target system, like access time.
+    ///
+    /// # Errors
+    /// Returns error if failed to stat file.
+    pub fn metadata<P: AsRef<Path>>(&self, path: P) -> Result<fs::Metadata, Error> {
+        let path = path.as_ref();
+        fs::metadata(self.join(path)).map_err(|err| Error::from_io(path, err))
+    }
+
+    /// Read content of file at `path` in target system.
+    ///
+    /// # Errors
+    /// Returns error if failed to read file, the error holds `path` in target system.
+    pub fn read<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>, Error> {
+        let path = path.as_ref();
+        let real_path = self.join(path);
+        let content = fs::read(&real_path).map_err(|err| Error::from_io(path, err))?;
+        if let Some(recorder) = &self.recorder {
+            recorder.add_file(path, &content);
+        }
+        Ok(content)
+    }
+
+    /// Read content of text file at `path` in target system.
+    ///
+    /// # Errors
+    /// Returns error if failed to read file or file content is not valid UTF-8.
+    pub fn read_to_string<P: AsRef<Path>>(&self, path: P) -> Result<String, Error> {
+        let path = path.as_ref();
+        let content = self.read(path)?;
+        String::from_utf8(content).map_err(|err| {
+            Error::malformed(
+                path,
+                None,
+                "utf-8",
+                &String::from_utf8_lossy(err.as_bytes()),
+            )
+        })
+    }
+
+    /// Get sorted file names in directory at `path` in target system.
+    ///
+    /// # Errors
+    /// Returns error if failed to read directory.
+    pub fn read_dir<P: AsRef<Path>>(&self, path: P) -> Result<Vec<String>, Error> {
+        let path = path.as_ref();
+        let real_path = self.join(path);
+        let to_error = |err| Error::from_io(path, err);
+
+        let mut names = Vec::new();
+        for entry in fs::read_dir(&real_path).map_err(to_error)? {
+            let entry = entry.map_err(to_error)?;
+            if let Some(name) = entry.file_name().to_str() {
+                names.push(name.to_owned());
+            } else {
+                log::warn!(
+                    "Invalid file name in {}: {:?}",
+                    real_path.display(),
+                    entry.file_name()
+                );
+            }
+        }
+        names.sort();
+        if let Some(recorder) = &self.recorder {
+            recorder.add_dir(path);
+        }
+        Ok(names)
+    }
+}
+
+#[cfg(test)]
+impl SystemRoot {
+    /// Fixture tree at `tests/fixtures/{name}`.
+    pub(crate) fn fixture(name: &str) -> Self {
+        Self::new(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("tests/fixtures")
+                .join(name),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::SystemRoot;
+
+    #[test]
+    fn test_join() {
+        let root = SystemRoot::default();
+        assert_eq!(root.join("/proc/modules"), PathBuf::from("/proc/modules"));
+
+        let root = SystemRoot::new("/mnt/rootfs");
+        assert_eq!(
+            root.join("/proc/modules"),
+            PathBuf::from("/mnt/rootfs/proc/modules")
+        );
+        assert_eq!(
+            root.join("etc/resolv.conf"),
+            PathBuf::from("/mnt/rootfs/etc/resolv.conf")
+        );
+    }
+}
@@ -2,6 +2,8 @@
 // Use of this source is governed by General Public License that can be found
 // in the LICENSE file.
 
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
 #[must_use]
 pub fn parse_mem_size(s: &str) -> Option<i64> {
     if s.is_empty() {
@@ -33,9 +35,52 @@ pub fn parse_mem_size(s: &str) -> Option<i64> {
     Some(size)
 }
 
+/// Format duration like `3 days, 04:25:13`.
+#[must_use]
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let days = secs / 86400;
+    let hours = secs % 86400 / 3600;
+    let minutes = secs % 3600 / 60;
+    let seconds = secs % 60;
+    match days {
+        0 => format!("{hours:02}:{minutes:02}:{seconds:02}"),
+        1 => format!("1 day, {hours:02}:{minutes:02}:{seconds:02}"),
+        _ => format!("{days} days, {hours:02}:{minutes:02}:{seconds:02}"),
+    }
+}
+
+/// Format time in UTC, like `2023-10-16 05:00:00`.
+#[must_use]
+pub fn format_time(time: SystemTime) -> String {
+    let secs = match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs(),
+        Err(_err) => 0,
+    };
+    let days = secs / 86400;
+    let hours = secs % 86400 / 3600;
+    let minutes = secs % 3600 / 60;
+    let seconds = secs % 60;
+
+    // Convert days since epoch to civil date, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+
+    format!("{year:04}-{month:02}-{day:02} {hours:02}:{minutes:02}:{seconds:02}")
+}
+
 #[cfg(test)]
 mod tests {
-    use super::parse_mem_size;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::{format_duration, format_time, parse_mem_size};
 
     #[test]
     fn test_parse_mem_size() {
@@ -52,4 +97,31 @@ mod tests {
             assert_eq!(parse_mem_size(key), *value);
         }
     }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_secs(45)), "00:00:45");
+        assert_eq!(format_duration(Duration::from_secs(12_345)), "03:25:45");
+        assert_eq!(
+            format_duration(Duration::from_secs(90_061)),
+            "1 day, 01:01:01"
+        );
+        assert_eq!(
+            format_duration(Duration::from_secs(3 * 86400 + 59)),
+            "3 days, 00:00:59"
+        );
+    }
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!(format_time(UNIX_EPOCH), "1970-01-01 00:00:00");
+        assert_eq!(
+            format_time(UNIX_EPOCH + Duration::from_secs(1_697_432_400)),
+            "2023-10-16 05:00:00"
+        );
+        assert_eq!(
+            format_time(UNIX_EPOCH + Duration::from_secs(951_782_400)),
+            "2000-02-29 00:00:00"
+        );
+    }
 }
@@ -0,0 +1,87 @@
+// Copyright (c) 2023 Xu Shaohua <shaohua@biofan.org>. All rights reserved.
+// Use of this source is governed by General Public License that can be found
+// in the LICENSE file.
+
+//! Read login records in utmp format, like `/var/log/wtmp` and `/run/utmp`.
+
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use utmp_rs::{Utmp32Parser, UtmpEntry};
+
+use crate::base::system_root::SystemRoot;
+use crate::error::Error;
+
+/// Size of utmp record with 32-bit time layout.
+const RECORD_SIZE: usize = 384;
+
+/// Raw fields of utmp record.
+///
+/// `utmp_rs` only keeps pid and time of `LOGIN_PROCESS` records, but records in
+/// `/var/log/btmp` store user name, tty and remote host in that type.
+#[derive(Debug, Clone)]
+pub struct Record {
+    pub pid: i32,
+    pub line: String,
+    pub user: String,
+    pub host: String,
+    pub time: SystemTime,
+}
+
+/// Read all records in utmp file at `path`.
+///
+/// glibc on 64-bit platforms keeps the 32-bit time layout of utmp for
+/// compatibility, so records are always parsed with 32-bit layout.
+pub fn read_entries<P: AsRef<Path>>(root: &SystemRoot, path: P) -> Result<Vec<UtmpEntry>, Error> {
+    let path = path.as_ref();
+    let content = root.read(path)?;
+    Utmp32Parser::from_reader(content.as_slice())
+        .enumerate()
+        .map(|(index, entry)| entry.map_err(|err| record_error(path, index, &err.to_string())))
+        .collect()
+}
+
+/// Read raw records in utmp file at `path`, with the same layout as [`read_entries`].
+pub fn read_records<P: AsRef<Path>>(root: &SystemRoot, path: P) -> Result<Vec<Record>, Error> {
+    let path = path.as_ref();
+    let content = root.read(path)?;
+    if content.len() % RECORD_SIZE != 0 {
+        return Err(record_error(
+            path,
+            content.len() / RECORD_SIZE,
+            "size not aligned",
+        ));
+    }
+
+    Ok(content
+        .chunks_exact(RECORD_SIZE)
+        .map(parse_record)
+        .collect())
+}
+
+fn record_error(path: &Path, index: usize, reason: &str) -> Error {
+    Error::malformed(path, None, "record", &format!("#{}: {reason}", index + 1))
+}
+
+fn parse_record(buf: &[u8]) -> Record {
+    let i32_at = |offset: usize| {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(&buf[offset..offset + 4]);
+        i32::from_ne_bytes(bytes)
+    };
+    let str_at = |start: usize, end: usize| {
+        let field = &buf[start..end];
+        let len = field.iter().position(|b| *b == 0).unwrap_or(field.len());
+        String::from_utf8_lossy(&field[..len]).into_owned()
+    };
+
+    let secs = u64::try_from(i32_at(340)).unwrap_or_default();
+    let usecs = u64::try_from(i32_at(344)).unwrap_or_default();
+    Record {
+        pid: i32_at(4),
+        line: str_at(8, 40),
+        user: str_at(44, 76),
+        host: str_at(76, 332),
+        time: UNIX_EPOCH + Duration::from_secs(secs) + Duration::from_micros(usecs),
+    }
+}
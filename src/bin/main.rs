@@ -2,4 +2,1072 @@
 // Use of this source is governed by General Public License that can be found
 // in the LICENSE file.
 
-fn main() {}
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+
+use xingtian::base::parse_mode::{ParseMode, Parsed};
+use xingtian::base::system_root::SystemRoot;
+use xingtian::base::unit::{format_duration, format_time};
+use xingtian::computer::{
+    bootup, environment, failed_login, group, language, module, module_graph, os, search_path,
+    session, uptime, user,
+};
+use xingtian::device::{power_supply, usb};
+use xingtian::metrics;
+use xingtian::network::{arp_table, dev, dns_server, routing_table};
+use xingtian::report::diff::ReportDiff;
+use xingtian::report::{html, markdown, SystemReport};
+
+#[derive(Debug, Parser)]
+#[command(name = "xingtian", version, about = "System info")]
+struct Cli {
+    /// Root directory of target system, like a chroot or a snapshot directory.
+    #[arg(long, global = true, default_value = "/")]
+    root: PathBuf,
+
+    /// Print as json instead of table.
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Skip records which failed to parse and print them as warnings.
+    #[arg(long, global = true)]
+    lenient: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// List loaded kernel modules.
+    Modules {
+        /// Show modinfo of this module instead, like `modinfo`.
+        #[arg(long)]
+        detail: Option<String>,
+    },
+
+    /// Show dependency graph of loaded modules.
+    Modgraph {
+        /// Show dependencies, dependents and unload order of this module.
+        name: Option<String>,
+
+        /// Output format of whole graph, ignored with `--json`.
+        #[arg(long, value_enum, default_value_t = GraphFormat::Dot)]
+        format: GraphFormat,
+    },
+
+    /// List usb devices.
+    Usb,
+
+    /// Network interfaces.
+    Net {
+        #[command(subcommand)]
+        command: NetCommand,
+    },
+
+    /// List ipv4 routing table.
+    Routes,
+
+    /// List arp table.
+    Arp,
+
+    /// List dns servers.
+    Dns,
+
+    /// List batteries.
+    Battery,
+
+    /// Show time since boot.
+    Uptime {
+        /// Also show idle time, boot time, load averages and time in suspend.
+        #[arg(long)]
+        detail: bool,
+    },
+
+    /// Show distribution, kernel, host name and desktop environment.
+    Os,
+
+    /// List logged-in users, like `who`.
+    Who,
+
+    /// List groups in /etc/group.
+    Groups {
+        /// Enumerate groups of current system through NSS, like LDAP groups.
+        #[arg(long)]
+        nss: bool,
+
+        /// Only list groups which this user is a member of.
+        #[arg(long)]
+        member: Option<String>,
+    },
+
+    /// List user accounts in /etc/passwd, with password aging in /etc/shadow.
+    Users {
+        /// Enumerate users of current system through NSS, like LDAP users.
+        #[arg(long)]
+        nss: bool,
+
+        /// Only list users with a login shell.
+        #[arg(long)]
+        login: bool,
+
+        /// Only list users whose password never expires.
+        #[arg(long)]
+        no_expiry: bool,
+    },
+
+    /// List generated locales and active locale settings.
+    Locales,
+
+    /// List failed login attempts, like `lastb`.
+    Lastb {
+        /// Path of btmp file in target system.
+        #[arg(long, default_value = failed_login::BTMP_FILE)]
+        file: PathBuf,
+
+        /// Group attempts by source host or by user.
+        #[arg(long, value_enum)]
+        group: Option<GroupBy>,
+
+        /// Only count attempts in last N hours.
+        #[arg(long)]
+        hours: Option<u64>,
+    },
+
+    /// List boot history, like `last -x reboot`.
+    Boots {
+        /// Path of wtmp file in target system.
+        #[arg(long, default_value = bootup::WTMP_FILE)]
+        file: PathBuf,
+    },
+
+    /// List environment variables, secret values are hidden.
+    Env {
+        /// Read environment of this process instead of current one.
+        #[arg(long)]
+        pid: Option<i32>,
+
+        /// Compare with environment of this process.
+        #[arg(long, value_name = "PID")]
+        diff: Option<i32>,
+
+        /// Show values of secret variables, like `*_TOKEN` and `*PASSWORD*`.
+        #[arg(long)]
+        show_secrets: bool,
+    },
+
+    /// Check entries of PATH, LD_LIBRARY_PATH, LD_PRELOAD, MANPATH and XDG_* variables.
+    Paths {
+        /// Read environment of this process instead of current one.
+        #[arg(long)]
+        pid: Option<i32>,
+
+        /// Show which entry provides this file, like `which -a`.
+        #[arg(long, value_name = "NAME")]
+        which: Option<String>,
+
+        /// Variable to search with `--which`.
+        #[arg(long, default_value = "PATH")]
+        var: String,
+    },
+
+    /// Generate full system report.
+    Report {
+        /// Output format.
+        #[arg(long, value_enum, default_value_t = ReportFormat::Markdown)]
+        format: ReportFormat,
+    },
+
+    /// Compare two reports generated by `report --format json`.
+    Diff {
+        /// Report taken earlier.
+        old: PathBuf,
+
+        /// Report taken later.
+        new: PathBuf,
+    },
+
+    /// Print metrics in OpenMetrics text format.
+    Metrics {
+        /// Serve metrics at http://<LISTEN>/metrics instead of printing them.
+        #[arg(long)]
+        listen: Option<SocketAddr>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ReportFormat {
+    Html,
+    Markdown,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum GraphFormat {
+    Dot,
+    Text,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum GroupBy {
+    Host,
+    User,
+}
+
+#[derive(Debug, Subcommand)]
+enum NetCommand {
+    /// List network interfaces and their statistics.
+    Dev,
+}
+
+fn main() {
+    let cli = Cli::parse();
+    if let Err(err) = run(&cli) {
+        eprintln!("Error: {err}");
+        process::exit(1);
+    }
+}
+
+fn run(cli: &Cli) -> Result<(), Box<dyn Error>> {
+    let root = SystemRoot::new(&cli.root);
+    let mode = if cli.lenient {
+        ParseMode::Lenient
+    } else {
+        ParseMode::Strict
+    };
+
+    match cli.command {
+        Command::Modules {
+            detail: Some(ref name),
+        } => {
+            let detail = module::get_detail(&root, name)?;
+            if cli.json {
+                return print_json(&detail);
+            }
+            let mut rows = vec![
+                ("filename", detail.path.clone()),
+                ("description", detail.description.clone()),
+                ("author", detail.author.clone()),
+                ("license", detail.license.clone()),
+                ("depends", detail.dependencies.join(",")),
+                ("vermagic", detail.version_magic.clone()),
+                ("signer", detail.signer.clone()),
+                ("sig_key", detail.sig_key.clone()),
+            ];
+            rows.extend(detail.aliases.iter().map(|alias| ("alias", alias.clone())));
+            rows.extend(detail.firmware.iter().map(|fw| ("firmware", fw.clone())));
+            rows.extend(detail.parameters.iter().map(|p| {
+                (
+                    "parm",
+                    format!("{}:{} ({})", p.name, p.description, p.param_type),
+                )
+            }));
+            for (key, value) in rows {
+                if !value.is_empty() {
+                    println!("{:<12} {value}", format!("{key}:"));
+                }
+            }
+        }
+        Command::Modules { detail: None } => {
+            let mut list = module::get_list(&root)?;
+            list.extend(module::get_builtin_list(&root)?);
+            if cli.json {
+                return print_json(&list);
+            }
+            let rows = list
+                .iter()
+                .map(|m| {
+                    let state = if m.builtin {
+                        "builtin".to_owned()
+                    } else {
+                        format!("{:?}", m.state)
+                    };
+                    vec![
+                        m.name.clone(),
+                        m.memory_bytes.to_string(),
+                        m.refcount
+                            .map(|count| count.to_string())
+                            .unwrap_or_default(),
+                        m.dependencies.join(","),
+                        state,
+                        m.taint.clone(),
+                        if m.blacklisted && !m.builtin {
+                            "loaded anyway".to_owned()
+                        } else {
+                            String::new()
+                        },
+                        m.configured_options.join(" "),
+                        m.ignored_options().join(" "),
+                        m.parameters
+                            .iter()
+                            .map(|p| format!("{}={}", p.name, p.value))
+                            .collect::<Vec<_>>()
+                            .join(" "),
+                    ]
+                })
+                .collect();
+            print_table(
+                &[
+                    "Module",
+                    "Size",
+                    "Refs",
+                    "Used by",
+                    "State",
+                    "Taint",
+                    "Blacklisted",
+                    "Configured options",
+                    "Ignored options",
+                    "Parameters",
+                ],
+                rows,
+            );
+        }
+        Command::Modgraph { ref name, format } => {
+            let graph = module_graph::get_graph(&root)?;
+            match name {
+                Some(name) => {
+                    let dependencies = graph.closure(name);
+                    let dependents = graph.reverse_closure(name);
+                    let unload_order = graph.unload_order(name);
+                    if cli.json {
+                        return print_json(&serde_json::json!({
+                            "dependencies": dependencies,
+                            "dependents": dependents,
+                            "unload_order": unload_order,
+                        }));
+                    }
+                    println!("Depends on:   {}", dependencies.join(" "));
+                    println!("Required by:  {}", dependents.join(" "));
+                    println!("Unload order: {}", unload_order.join(" "));
+                }
+                None if cli.json => return print_json(&graph),
+                None => match format {
+                    GraphFormat::Dot => print!("{}", graph.to_dot()),
+                    GraphFormat::Text => {
+                        let rows = graph
+                            .dependencies
+                            .keys()
+                            .map(|name| {
+                                vec![
+                                    name.clone(),
+                                    graph.dependencies_of(name).join(","),
+                                    graph.dependents_of(name).join(","),
+                                ]
+                            })
+                            .collect();
+                        print_table(&["Module", "Depends on", "Required by"], rows);
+                    }
+                },
+            }
+        }
+        Command::Usb => {
+            let list = usb::scan_usb(&root)?;
+            if cli.json {
+                return print_json(&list);
+            }
+            let rows = list
+                .iter()
+                .map(|d| {
+                    let vendor = if d.vendor_from_database.is_empty() {
+                        &d.manufacturer
+                    } else {
+                        &d.vendor_from_database
+                    };
+                    let product = if d.model_from_database.is_empty() {
+                        &d.product
+                    } else {
+                        &d.model_from_database
+                    };
+                    vec![
+                        d.bus_num.clone(),
+                        d.dev_num.clone(),
+                        format!("{}:{}", d.vendor_id, d.model_id),
+                        vendor.clone(),
+                        product.clone(),
+                        d.speed.clone(),
+                    ]
+                })
+                .collect();
+            print_table(&["Bus", "Device", "ID", "Vendor", "Product", "Speed"], rows);
+        }
+        Command::Net {
+            command: NetCommand::Dev,
+        } => {
+            let list = print_warnings(dev::get_list_with_mode(&root, mode)?);
+            if cli.json {
+                return print_json(&list);
+            }
+            let rows = list
+                .iter()
+                .map(|d| {
+                    vec![
+                        d.interface.clone(),
+                        format!("{:?}", d.dev_type),
+                        d.mac.clone(),
+                        d.mtu.to_string(),
+                        d.received_bytes.to_string(),
+                        d.received_packets.to_string(),
+                        d.transmit_bytes.to_string(),
+                        d.transmit_packets.to_string(),
+                    ]
+                })
+                .collect();
+            print_table(
+                &[
+                    "Interface",
+                    "Type",
+                    "MAC",
+                    "MTU",
+                    "RX bytes",
+                    "RX packets",
+                    "TX bytes",
+                    "TX packets",
+                ],
+                rows,
+            );
+        }
+        Command::Routes => {
+            let list = print_warnings(routing_table::get_list_with_mode(&root, mode)?);
+            if cli.json {
+                return print_json(&list);
+            }
+            let to_ip = |s: &str| {
+                routing_table::hex_to_ipv4(s).map_or_else(|| s.to_owned(), |ip| ip.to_string())
+            };
+            let rows = list
+                .iter()
+                .map(|r| {
+                    vec![
+                        to_ip(&r.destination),
+                        to_ip(&r.gateway),
+                        to_ip(&r.mask),
+                        r.flags.clone(),
+                        r.metric.to_string(),
+                        r.interface.clone(),
+                    ]
+                })
+                .collect();
+            print_table(
+                &["Destination", "Gateway", "Mask", "Flags", "Metric", "Iface"],
+                rows,
+            );
+        }
+        Command::Arp => {
+            let list = print_warnings(arp_table::get_list_with_mode(&root, mode)?);
+            if cli.json {
+                return print_json(&list);
+            }
+            let rows = list
+                .iter()
+                .map(|a| {
+                    vec![
+                        a.ip.clone(),
+                        a.hw_type.clone(),
+                        a.flags.clone(),
+                        a.hw_address.clone(),
+                        a.device.clone(),
+                    ]
+                })
+                .collect();
+            print_table(&["IP", "HW type", "Flags", "HW address", "Device"], rows);
+        }
+        Command::Dns => {
+            let list = dns_server::get_list(&root)?;
+            if cli.json {
+                return print_json(&list);
+            }
+            let rows = list
+                .iter()
+                .map(|d| vec![d.ip.clone(), d.hostname.clone()])
+                .collect();
+            print_table(&["IP", "Hostname"], rows);
+        }
+        Command::Battery => {
+            let list = print_warnings(power_supply::get_list_with_mode(&root, mode)?);
+            if cli.json {
+                return print_json(&list);
+            }
+            let rows = list
+                .iter()
+                .map(|b| {
+                    vec![
+                        b.name.clone(),
+                        format!("{} {}", b.manufacturer, b.model_name),
+                        format!("{:?}", b.status),
+                        format!("{}%", b.capacity),
+                        format!("{}/{}", b.charge_now, b.charge_full),
+                        b.charge_full_design.to_string(),
+                        b.voltage_now.to_string(),
+                        b.cycle_count.to_string(),
+                    ]
+                })
+                .collect();
+            print_table(
+                &[
+                    "Name", "Model", "Status", "Capacity", "Charge", "Design", "Voltage", "Cycles",
+                ],
+                rows,
+            );
+        }
+        Command::Uptime { detail: true } => {
+            let info = uptime::get_uptime_info(&root)?;
+            // Clocks are only meaningful for the running system.
+            let clocks = if root.root() == Path::new("/") {
+                Some(uptime::get_clocks()?)
+            } else {
+                None
+            };
+            if cli.json {
+                return print_json(&serde_json::json!({ "info": info, "clocks": clocks }));
+            }
+            let load = &info.load_average;
+            let mut rows = vec![
+                vec!["Uptime".to_owned(), format_duration(info.uptime)],
+                vec!["Boot time".to_owned(), format_time(info.boot_time)],
+                vec![
+                    "Idle per CPU".to_owned(),
+                    format_duration(info.idle_per_cpu()),
+                ],
+                vec![
+                    "Idle ratio".to_owned(),
+                    format!("{:.1}%", info.idle_ratio() * 100.0),
+                ],
+                vec![
+                    "Load average".to_owned(),
+                    format!("{:.2} {:.2} {:.2}", load.one, load.five, load.fifteen),
+                ],
+                vec![
+                    "Tasks".to_owned(),
+                    format!("{} running, {} total", load.running, load.total),
+                ],
+            ];
+            if let Some(clocks) = clocks {
+                rows.push(vec![
+                    "Suspended".to_owned(),
+                    format_duration(clocks.suspended()),
+                ]);
+            }
+            print_table(&["Item", "Value"], rows);
+        }
+        Command::Uptime { detail: false } => {
+            let uptime = uptime::get_uptime(&root)?;
+            if cli.json {
+                return print_json(&uptime.as_secs_f64());
+            }
+            println!("{}", format_duration(uptime));
+        }
+        Command::Os => {
+            let os = os::get_os(&root)?;
+            if cli.json {
+                return print_json(&os);
+            }
+            let kernel = &os.kernel;
+            let rows = vec![
+                vec!["Distribution".to_owned(), os.release.pretty_name],
+                vec!["ID".to_owned(), os.release.id],
+                vec!["Version".to_owned(), os.release.version_id],
+                vec!["Codename".to_owned(), os.release.version_codename],
+                vec![
+                    "Kernel".to_owned(),
+                    format!("{} {} {}", kernel.sysname, kernel.release, kernel.machine),
+                ],
+                vec!["Hostname".to_owned(), os.hostname],
+                vec!["Domain".to_owned(), os.domain],
+                vec!["Desktop".to_owned(), os.desktop.desktop],
+                vec!["Session type".to_owned(), os.desktop.session_type],
+            ];
+            print_table(&["Item", "Value"], rows);
+        }
+        Command::Who => {
+            let list = session::get_list(&root)?;
+            if cli.json {
+                return print_json(&list);
+            }
+            let rows = list
+                .iter()
+                .map(|s| {
+                    vec![
+                        s.user.clone(),
+                        s.tty.clone(),
+                        s.host.clone(),
+                        format_time(s.login_time),
+                        s.idle.map(format_duration).unwrap_or_default(),
+                        s.pid.to_string(),
+                    ]
+                })
+                .collect();
+            print_table(&["User", "TTY", "From", "Login", "Idle", "PID"], rows);
+        }
+        Command::Groups { nss, ref member } => {
+            let list = if nss {
+                group::get_group_list_nss()
+            } else {
+                group::get_group_list(&root)?
+            };
+            let list: Vec<&group::Group> = match member {
+                Some(member) => group::find_by_member(&list, member),
+                None => list.iter().collect(),
+            };
+            if cli.json {
+                return print_json(&list);
+            }
+            let rows = list
+                .iter()
+                .map(|g| {
+                    vec![
+                        g.name.clone(),
+                        g.gid.to_string(),
+                        g.members.join(","),
+                        g.admins.join(","),
+                    ]
+                })
+                .collect();
+            print_table(&["Name", "GID", "Members", "Admins"], rows);
+        }
+        Command::Locales => {
+            let list = language::get_list(&root)?;
+            let active = language::get_active_locale(&root)?;
+            if cli.json {
+                return print_json(&serde_json::json!({ "installed": list, "active": active }));
+            }
+            let rows = list
+                .iter()
+                .map(|l| {
+                    vec![
+                        l.locale.clone(),
+                        l.language.clone(),
+                        l.territory.clone(),
+                        l.code_set.clone(),
+                        l.revision.clone(),
+                    ]
+                })
+                .collect();
+            print_table(
+                &["Locale", "Language", "Territory", "Code Set", "Revision"],
+                rows,
+            );
+
+            println!();
+            let rows = active
+                .vars
+                .iter()
+                .map(|var| {
+                    let installed = language::is_builtin_locale(&var.value)
+                        || var.name == "LANGUAGE"
+                        || language::find_locale(&list, &var.value).is_some();
+                    vec![
+                        var.name.clone(),
+                        var.value.clone(),
+                        format!("{:?}", var.source),
+                        if installed { "" } else { "missing" }.to_owned(),
+                    ]
+                })
+                .collect();
+            print_table(&["Name", "Value", "Source", "Generated"], rows);
+        }
+        Command::Users {
+            nss,
+            login,
+            no_expiry,
+        } => {
+            let list = if nss {
+                user::get_user_list_nss()
+            } else {
+                user::get_user_list(&root)?
+            };
+            let list: Vec<&user::User> = list
+                .iter()
+                .filter(|u| !login || u.has_login_shell())
+                .filter(|u| !no_expiry || u.password_never_expires() == Some(true))
+                .collect();
+            if cli.json {
+                return print_json(&list);
+            }
+            let rows = list
+                .iter()
+                .map(|u| {
+                    let (password, expires) = u.shadow.as_ref().map_or_else(
+                        || (String::new(), String::new()),
+                        |s| {
+                            let expires = if s.password_expires() {
+                                s.password_expire().map(format_time).unwrap_or_default()
+                            } else {
+                                "never".to_owned()
+                            };
+                            (format!("{:?}", s.password), expires)
+                        },
+                    );
+                    vec![
+                        u.name.clone(),
+                        u.uid.to_string(),
+                        u.gid.to_string(),
+                        u.home.clone(),
+                        u.shell.clone(),
+                        password,
+                        expires,
+                        u.groups.join(","),
+                    ]
+                })
+                .collect();
+            print_table(
+                &[
+                    "Name", "UID", "GID", "Home", "Shell", "Password", "Expires", "Groups",
+                ],
+                rows,
+            );
+        }
+        Command::Lastb {
+            ref file,
+            group,
+            hours,
+        } => {
+            // Count all attempts if `hours` is too large.
+            let since = hours
+                .and_then(|hours| hours.checked_mul(3600))
+                .and_then(|secs| SystemTime::now().checked_sub(Duration::from_secs(secs)))
+                .unwrap_or(UNIX_EPOCH);
+            let list: Vec<_> = failed_login::get_list_from(&root, file)?
+                .into_iter()
+                .filter(|item| item.time >= since)
+                .collect();
+            if let Some(group) = group {
+                let groups = match group {
+                    GroupBy::Host => failed_login::group_by_host(&list, ..),
+                    GroupBy::User => failed_login::group_by_user(&list, ..),
+                };
+                if cli.json {
+                    return print_json(&groups);
+                }
+                let rows = groups
+                    .iter()
+                    .map(|a| {
+                        vec![
+                            a.key.clone(),
+                            a.count.to_string(),
+                            a.related.join(","),
+                            format_time(a.first_time),
+                            format_time(a.last_time),
+                        ]
+                    })
+                    .collect();
+                let key = match group {
+                    GroupBy::Host => "Host",
+                    GroupBy::User => "User",
+                };
+                print_table(&[key, "Attempts", "Related", "First", "Last"], rows);
+                return Ok(());
+            }
+            if cli.json {
+                return print_json(&list);
+            }
+            let rows = list
+                .iter()
+                .map(|item| {
+                    vec![
+                        item.user.clone(),
+                        item.tty.clone(),
+                        item.host.clone(),
+                        format_time(item.time),
+                    ]
+                })
+                .collect();
+            print_table(&["User", "TTY", "From", "Time"], rows);
+        }
+        Command::Boots { ref file } => {
+            let list = bootup::get_list_from(&root, file)?;
+            if cli.json {
+                return print_json(&list);
+            }
+            let rows = list
+                .iter()
+                .map(|b| {
+                    let end = match (b.end, b.end_time) {
+                        (bootup::BootEnd::Shutdown, Some(time)) => format_time(time),
+                        (bootup::BootEnd::Crash, _) => "crash".to_owned(),
+                        _ => "still running".to_owned(),
+                    };
+                    vec![
+                        b.kernel.clone(),
+                        format_time(b.start_time),
+                        end,
+                        b.duration().map(format_duration).unwrap_or_default(),
+                    ]
+                })
+                .collect();
+            print_table(&["Kernel", "Boot", "End", "Duration"], rows);
+        }
+        Command::Env {
+            pid,
+            diff,
+            show_secrets,
+        } => {
+            let policy = if show_secrets {
+                environment::RedactPolicy::none()
+            } else {
+                environment::RedactPolicy::default()
+            };
+            let mut list = match pid {
+                Some(pid) => environment::get_process_environment(&root, pid)?,
+                None => environment::get_environment(),
+            };
+            let lossy = |s: &std::ffi::OsStr| s.to_string_lossy().into_owned();
+
+            if let Some(other) = diff {
+                let other = environment::get_process_environment(&root, other)?;
+                let mut diff = environment::EnvDiff::new(&list, &other);
+                diff.redact(&policy);
+                if cli.json {
+                    let to_map = |list: &[environment::EnvItem]| {
+                        list.iter()
+                            .map(|item| (lossy(&item.key), lossy(&item.value).into()))
+                            .collect::<serde_json::Map<String, serde_json::Value>>()
+                    };
+                    let changed: Vec<serde_json::Value> = diff
+                        .changed
+                        .iter()
+                        .map(|change| {
+                            serde_json::json!({
+                                "key": lossy(&change.key),
+                                "old_value": lossy(&change.old_value),
+                                "new_value": lossy(&change.new_value),
+                            })
+                        })
+                        .collect();
+                    return print_json(&serde_json::json!({
+                        "added": to_map(&diff.added),
+                        "removed": to_map(&diff.removed),
+                        "changed": changed,
+                    }));
+                }
+                let mut rows = Vec::new();
+                for item in &diff.removed {
+                    rows.push(vec![
+                        "-".to_owned(),
+                        lossy(&item.key),
+                        lossy(&item.value),
+                        String::new(),
+                    ]);
+                }
+                for item in &diff.added {
+                    rows.push(vec![
+                        "+".to_owned(),
+                        lossy(&item.key),
+                        String::new(),
+                        lossy(&item.value),
+                    ]);
+                }
+                for change in &diff.changed {
+                    rows.push(vec![
+                        "~".to_owned(),
+                        lossy(&change.key),
+                        lossy(&change.old_value),
+                        lossy(&change.new_value),
+                    ]);
+                }
+                print_table(&["", "Key", "Old", "New"], rows);
+                return Ok(());
+            }
+
+            policy.redact(&mut list);
+            let rows: Vec<Vec<String>> = list
+                .iter()
+                .map(|item| vec![lossy(&item.key), lossy(&item.value)])
+                .collect();
+            if cli.json {
+                let map: serde_json::Map<String, serde_json::Value> = rows
+                    .into_iter()
+                    .map(|row| {
+                        let mut row = row.into_iter();
+                        let key = row.next().unwrap_or_default();
+                        let value = row.next().unwrap_or_default();
+                        (key, value.into())
+                    })
+                    .collect();
+                return print_json(&map);
+            }
+            print_table(&["Key", "Value"], rows);
+        }
+        Command::Paths {
+            pid,
+            ref which,
+            ref var,
+        } => {
+            let env = match pid {
+                Some(pid) => environment::get_process_environment(&root, pid)?,
+                None => environment::get_environment(),
+            };
+            let list = search_path::analyze(&root, &env);
+
+            if let Some(name) = which {
+                // Relative entries are searched in working directory of process.
+                let cwd = match pid {
+                    Some(pid) => environment::get_process_cwd(&root, pid)?,
+                    None => std::env::current_dir()?,
+                };
+                let found = list
+                    .iter()
+                    .find(|item| &item.key == var)
+                    .map(|item| item.find(&root, &cwd, name))
+                    .unwrap_or_default();
+                if cli.json {
+                    return print_json(&found);
+                }
+                let rows = found
+                    .iter()
+                    .enumerate()
+                    .map(|(index, found)| {
+                        let state = if index == 0 { "wins" } else { "shadowed" };
+                        let entry = if found.relative { "relative" } else { "" };
+                        vec![
+                            found.path.display().to_string(),
+                            state.to_owned(),
+                            entry.to_owned(),
+                        ]
+                    })
+                    .collect();
+                print_table(&["Path", "State", "Entry"], rows);
+                return Ok(());
+            }
+
+            if cli.json {
+                return print_json(&list);
+            }
+            let mut rows = Vec::new();
+            for item in &list {
+                for (index, entry) in item.entries.iter().enumerate() {
+                    let issues: Vec<String> =
+                        entry.issues.iter().map(ToString::to_string).collect();
+                    rows.push(vec![
+                        item.key.clone(),
+                        index.to_string(),
+                        entry.path.display().to_string(),
+                        issues.join(", "),
+                    ]);
+                }
+            }
+            print_table(&["Variable", "Index", "Entry", "Issues"], rows);
+        }
+        Command::Report { format } => {
+            let report = SystemReport::collect(&root);
+            if cli.json {
+                return print_json(&report);
+            }
+            match format {
+                ReportFormat::Html => print!("{}", html::render(&report)),
+                ReportFormat::Markdown => print!("{}", markdown::render(&report)),
+                ReportFormat::Json => return print_json(&report),
+            }
+        }
+        Command::Diff { ref old, ref new } => {
+            let read_report = |path: &Path| -> Result<SystemReport, Box<dyn Error>> {
+                let file = File::open(path)
+                    .map_err(|err| format!("Failed to open {}: {err}", path.display()))?;
+                Ok(serde_json::from_reader(BufReader::new(file))?)
+            };
+            let diff = ReportDiff::new(&read_report(old)?, &read_report(new)?);
+            if cli.json {
+                return print_json(&diff);
+            }
+            print!("{diff}");
+        }
+        Command::Metrics { listen } => {
+            if let Some(addr) = listen {
+                serve_metrics(&root, addr)?;
+            } else {
+                print!("{}", metrics::gather(&root));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Timeout of reading request and writing response of metrics endpoint.
+const METRICS_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Max length of request line of metrics endpoint, in bytes.
+const MAX_REQUEST_LINE: u64 = 8192;
+
+fn serve_metrics(root: &SystemRoot, addr: SocketAddr) -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind(addr)?;
+    eprintln!("Serving metrics at http://{addr}/metrics");
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                if let Err(err) = handle_metrics_request(root, stream) {
+                    eprintln!("Failed to handle request: {err}");
+                }
+            }
+            Err(err) => eprintln!("Failed to accept connection: {err}"),
+        }
+    }
+    Ok(())
+}
+
+fn handle_metrics_request(root: &SystemRoot, mut stream: TcpStream) -> Result<(), Box<dyn Error>> {
+    // Requests are served one by one, do not let a slow client block the others.
+    stream.set_read_timeout(Some(METRICS_TIMEOUT))?;
+    stream.set_write_timeout(Some(METRICS_TIMEOUT))?;
+
+    let mut request_line = String::new();
+    BufReader::new((&stream).take(MAX_REQUEST_LINE)).read_line(&mut request_line)?;
+    let mut parts = request_line.split_ascii_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let path = parts.next().unwrap_or_default();
+
+    let (status, content_type, body) = if path.is_empty() || !request_line.ends_with('\n') {
+        ("400 Bad Request", "text/plain", String::new())
+    } else if method != "GET" {
+        ("405 Method Not Allowed", "text/plain", String::new())
+    } else if path == "/metrics" {
+        ("200 OK", metrics::CONTENT_TYPE, metrics::gather(root))
+    } else {
+        ("404 Not Found", "text/plain", String::new())
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()?;
+    Ok(())
+}
+
+fn print_warnings<T>(parsed: Parsed<T>) -> T {
+    for warning in &parsed.warnings {
+        eprintln!("Warning: {warning}");
+    }
+    parsed.value
+}
+
+fn print_json<T: Serialize + ?Sized>(value: &T) -> Result<(), Box<dyn Error>> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+fn print_table(headers: &[&str], rows: Vec<Vec<String>>) {
+    let mut widths: Vec<usize> = headers.iter().map(|header| header.len()).collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let headers = headers.iter().map(|header| (*header).to_owned()).collect();
+    for row in std::iter::once(headers).chain(rows) {
+        let line: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:width$}"))
+            .collect();
+        println!("{}", line.join("  ").trim_end());
+    }
+}
@@ -2,42 +2,149 @@
 // Use of this source is governed by General Public License that can be found
 // in the LICENSE file.
 
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use utmp_rs::UtmpEntry;
+
+use crate::base::system_root::SystemRoot;
+use crate::base::utmp;
 use crate::error::Error;
 
-#[derive(Debug, Default, Clone)]
+/// Default path of login history.
+pub const WTMP_FILE: &str = "/var/log/wtmp";
+
+/// How a boot session ended.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BootEnd {
+    /// System is still running.
+    Running,
+
+    /// System was shutdown normally.
+    Shutdown,
+
+    /// System was booted again without a shutdown record.
+    Crash,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Bootup {
-    pub username: String,
-    pub session_tty: String,
     pub kernel: String,
-    pub start_time: String,
-    pub end_time: String,
+    pub start_time: SystemTime,
+
+    /// Time of shutdown, or boot time of next boot if crashed.
+    pub end_time: Option<SystemTime>,
+    pub end: BootEnd,
+}
+
+impl Bootup {
+    /// Time between boot and shutdown, `None` if system is still running.
+    #[must_use]
+    pub fn duration(&self) -> Option<Duration> {
+        let end_time = self.end_time?;
+        Some(end_time.duration_since(self.start_time).unwrap_or_default())
+    }
+}
+
+/// Get boot history from `/var/log/wtmp`, newest first, like `last -x reboot`.
+///
+/// # Errors
+/// Returns error if failed to read or parse wtmp file.
+pub fn get_list(root: &SystemRoot) -> Result<Vec<Bootup>, Error> {
+    get_list_from(root, WTMP_FILE)
 }
 
-/// # Panics
-/// got panic if failed to parse wtmp
+/// Get boot history from wtmp file at `path`, like rotated `/var/log/wtmp.1`.
 ///
 /// # Errors
-/// Returns error if failed to parse bootup time.
-pub fn get_list() -> Result<Vec<Bootup>, Error> {
-    const FILE: &str = "/home/shaohua/Desktop/wtmp";
-    let entries = utmp_rs::parse_from_path(FILE);
-    for entry in entries.unwrap() {
-        println!("entry: {entry:#?}");
+/// Returns error if failed to read or parse wtmp file.
+pub fn get_list_from<P: AsRef<Path>>(root: &SystemRoot, path: P) -> Result<Vec<Bootup>, Error> {
+    let entries = utmp::read_entries(root, path)?;
+    let mut list: Vec<Bootup> = Vec::new();
+
+    for entry in entries {
+        match entry {
+            UtmpEntry::BootTime {
+                kernel_version,
+                time,
+            } => {
+                let time = SystemTime::from(time);
+                if let Some(last) = list.last_mut() {
+                    if last.end == BootEnd::Running {
+                        last.end = BootEnd::Crash;
+                        last.end_time = Some(time);
+                    }
+                }
+                list.push(Bootup {
+                    kernel: kernel_version,
+                    start_time: time,
+                    end_time: None,
+                    end: BootEnd::Running,
+                });
+            }
+            UtmpEntry::ShutdownTime { time, .. } => {
+                if let Some(last) = list.last_mut() {
+                    if last.end == BootEnd::Running {
+                        last.end = BootEnd::Shutdown;
+                        last.end_time = Some(SystemTime::from(time));
+                    }
+                }
+            }
+            _ => (),
+        }
     }
-    todo!()
+
+    list.reverse();
+    Ok(list)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::get_list;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::{get_list, get_list_from, BootEnd};
+    use crate::base::system_root::SystemRoot;
+    use crate::error::ErrorKind;
 
     #[test]
     fn test_get_list() {
-        let list = get_list();
+        let list = get_list(&SystemRoot::fixture("laptop"));
         assert!(list.is_ok());
         let list = list.unwrap();
-        assert!(!list.is_empty());
-        assert!(list.is_empty());
-        //ASSERT_LT(list.at(0).start_time, QDateTime::currentDateTime());
+        assert_eq!(list.len(), 3);
+
+        assert_eq!(list[0].kernel, "6.5.0-2-amd64");
+        assert_eq!(list[0].end, BootEnd::Running);
+        assert_eq!(list[0].duration(), None);
+
+        assert_eq!(list[1].end, BootEnd::Crash);
+        assert_eq!(list[1].end_time, Some(list[0].start_time));
+
+        assert_eq!(list[2].kernel, "6.1.0-13-amd64");
+        assert_eq!(
+            list[2].start_time,
+            UNIX_EPOCH + Duration::from_secs(1_697_432_400)
+        );
+        assert_eq!(list[2].end, BootEnd::Shutdown);
+        assert_eq!(list[2].duration(), Some(Duration::from_secs(7200)));
+    }
+
+    #[test]
+    fn test_get_list_from() {
+        let root = SystemRoot::fixture("laptop");
+        let list = get_list_from(&root, "/var/log/wtmp.1").unwrap();
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].end, BootEnd::Shutdown);
+
+        let err = get_list_from(&root, "/var/log/wtmp.2").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotPresent);
+    }
+
+    #[test]
+    fn test_get_list_truncated() {
+        let err = get_list(&SystemRoot::fixture("malformed")).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Malformed);
     }
 }
@@ -2,9 +2,30 @@
 // Use of this source is governed by General Public License that can be found
 // in the LICENSE file.
 
+use std::collections::BTreeMap;
 use std::env;
-use std::ffi::OsString;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::path::PathBuf;
 
+use crate::base::system_root::SystemRoot;
+use crate::error::Error;
+
+/// Value shown in place of secret values.
+pub const REDACTED: &str = "<redacted>";
+
+/// Default patterns of secret variable names, `*` matches any characters.
+pub const SECRET_PATTERNS: &[&str] = &[
+    "*_TOKEN",
+    "*PASSWORD*",
+    "*PASSWD*",
+    "*SECRET*",
+    "*_API_KEY",
+    "AWS_*",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct EnvItem {
     pub key: OsString,
     pub value: OsString,
@@ -16,17 +37,266 @@ impl From<(OsString, OsString)> for EnvItem {
     }
 }
 
+/// Variable whose value differs in two environments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvChange {
+    pub key: OsString,
+    pub old_value: OsString,
+    pub new_value: OsString,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct EnvDiff {
+    /// Variables only in new environment.
+    pub added: Vec<EnvItem>,
+
+    /// Variables only in old environment.
+    pub removed: Vec<EnvItem>,
+    pub changed: Vec<EnvChange>,
+}
+
+impl EnvDiff {
+    /// Compare `old` environment with `new` one, result is sorted by name.
+    #[must_use]
+    pub fn new(old: &[EnvItem], new: &[EnvItem]) -> Self {
+        let to_map = |list: &[EnvItem]| -> BTreeMap<OsString, OsString> {
+            list.iter()
+                .map(|item| (item.key.clone(), item.value.clone()))
+                .collect()
+        };
+        let old = to_map(old);
+        let mut new = to_map(new);
+
+        let mut diff = Self::default();
+        for (key, old_value) in old {
+            match new.remove(&key) {
+                None => diff.removed.push(EnvItem::from((key, old_value))),
+                Some(new_value) if new_value != old_value => diff.changed.push(EnvChange {
+                    key,
+                    old_value,
+                    new_value,
+                }),
+                Some(_) => (),
+            }
+        }
+        diff.added = new.into_iter().map(EnvItem::from).collect();
+        diff
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// Hide secret values, changed secrets are still listed.
+    pub fn redact(&mut self, policy: &RedactPolicy) {
+        policy.redact(&mut self.added);
+        policy.redact(&mut self.removed);
+        for change in &mut self.changed {
+            if policy.is_secret(&change.key) {
+                change.old_value = OsString::from(REDACTED);
+                change.new_value = OsString::from(REDACTED);
+            }
+        }
+    }
+}
+
+/// Names of variables whose values shall not be shown or saved.
+#[derive(Debug, Clone)]
+pub struct RedactPolicy {
+    /// Upper case patterns, `*` matches any characters.
+    patterns: Vec<String>,
+}
+
+impl Default for RedactPolicy {
+    fn default() -> Self {
+        Self::new(SECRET_PATTERNS)
+    }
+}
+
+impl RedactPolicy {
+    /// Create a policy with name `patterns`, matching is case insensitive.
+    #[must_use]
+    pub fn new<S: AsRef<str>>(patterns: &[S]) -> Self {
+        Self {
+            patterns: patterns
+                .iter()
+                .map(|pattern| pattern.as_ref().to_ascii_uppercase())
+                .collect(),
+        }
+    }
+
+    /// Policy which hides nothing.
+    #[must_use]
+    pub const fn none() -> Self {
+        Self {
+            patterns: Vec::new(),
+        }
+    }
+
+    pub fn add_pattern(&mut self, pattern: &str) {
+        self.patterns.push(pattern.to_ascii_uppercase());
+    }
+
+    #[must_use]
+    pub fn is_secret(&self, key: &OsStr) -> bool {
+        let key = key.to_string_lossy().to_ascii_uppercase();
+        self.patterns
+            .iter()
+            .any(|pattern| wildcard_match(pattern.as_bytes(), key.as_bytes()))
+    }
+
+    /// Replace values of secret variables in `list` with [`REDACTED`].
+    pub fn redact(&self, list: &mut [EnvItem]) {
+        for item in list {
+            if self.is_secret(&item.key) {
+                item.value = OsString::from(REDACTED);
+            }
+        }
+    }
+}
+
+/// Match `text` against `pattern` where `*` matches any characters.
+fn wildcard_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => (0..=text.len()).any(|start| wildcard_match(rest, &text[start..])),
+        Some((c, rest)) => text
+            .split_first()
+            .map_or(false, |(t, text)| t == c && wildcard_match(rest, text)),
+    }
+}
+
+/// Get environment variables of current process.
+#[must_use]
 pub fn get_environment() -> Vec<EnvItem> {
     env::vars_os().map(EnvItem::from).collect()
 }
 
+/// Get environment variables of process `pid` in `/proc/<pid>/environ`.
+///
+/// The file is only readable by owner of the process or root, this is the
+/// environment when the process started, later changes are not reflected.
+///
+/// # Errors
+/// Returns error if process does not exist, permission denied or failed to
+/// parse file content.
+pub fn get_process_environment(root: &SystemRoot, pid: i32) -> Result<Vec<EnvItem>, Error> {
+    let path = format!("/proc/{pid}/environ");
+    let content = root.read(&path)?;
+    content
+        .split(|b| *b == 0)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let pos = entry.iter().position(|b| *b == b'=').ok_or_else(|| {
+                Error::malformed(
+                    &path,
+                    None,
+                    "variable",
+                    &OsStr::from_bytes(entry).to_string_lossy(),
+                )
+            })?;
+            Ok(EnvItem {
+                key: OsString::from_vec(entry[..pos].to_vec()),
+                value: OsString::from_vec(entry[pos + 1..].to_vec()),
+            })
+        })
+        .collect()
+}
+
+/// Get current working directory of process `pid` in `/proc/<pid>/cwd`.
+///
+/// # Errors
+/// Returns error if process does not exist or permission denied.
+pub fn get_process_cwd(root: &SystemRoot, pid: i32) -> Result<PathBuf, Error> {
+    let path = format!("/proc/{pid}/cwd");
+    fs::read_link(root.join(&path)).map_err(|err| Error::from_io(path, err))
+}
+
 #[cfg(test)]
 mod tests {
-    use super::get_environment;
+    use std::ffi::{OsStr, OsString};
+    use std::path::PathBuf;
+
+    use super::{
+        get_environment, get_process_cwd, get_process_environment, EnvDiff, EnvItem, RedactPolicy,
+        REDACTED,
+    };
+    use crate::base::system_root::SystemRoot;
+    use crate::error::ErrorKind;
 
     #[test]
     fn test_get_environment() {
         let env_list = get_environment();
         assert!(!env_list.is_empty());
     }
+
+    #[test]
+    fn test_get_process_environment() {
+        let root = SystemRoot::fixture("laptop");
+        let list = get_process_environment(&root, 1042);
+        assert!(list.is_ok());
+        let list = list.unwrap();
+        assert_eq!(list.len(), 5);
+        assert_eq!(list[0].key, "LANG");
+        assert_eq!(list[0].value, "C.UTF-8");
+
+        assert_eq!(
+            get_process_cwd(&root, 1042).ok(),
+            Some(PathBuf::from("/usr/local/bin"))
+        );
+
+        let err = get_process_environment(&root, 1).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotPresent);
+
+        let err = get_process_environment(&SystemRoot::fixture("malformed"), 1042).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Malformed);
+        assert_eq!(
+            err.to_string(),
+            "Failed to parse `variable` in `/proc/1042/environ`, text: `oops`"
+        );
+    }
+
+    #[test]
+    fn test_redact_policy() {
+        let policy = RedactPolicy::default();
+        assert!(policy.is_secret(OsStr::new("GITHUB_TOKEN")));
+        assert!(policy.is_secret(OsStr::new("db_password")));
+        assert!(policy.is_secret(OsStr::new("AWS_ACCESS_KEY_ID")));
+        assert!(!policy.is_secret(OsStr::new("TOKEN")));
+        assert!(!policy.is_secret(OsStr::new("PATH")));
+        assert!(!RedactPolicy::none().is_secret(OsStr::new("GITHUB_TOKEN")));
+
+        let mut policy = RedactPolicy::new(&["HOME"]);
+        policy.add_pattern("lang*");
+        assert!(policy.is_secret(OsStr::new("LANGUAGE")));
+        assert!(!policy.is_secret(OsStr::new("GITHUB_TOKEN")));
+    }
+
+    #[test]
+    fn test_env_diff() {
+        let root = SystemRoot::fixture("laptop");
+        let service = get_process_environment(&root, 1042).unwrap();
+        let shell = get_process_environment(&root, 2077).unwrap();
+
+        let mut diff = EnvDiff::new(&service, &shell);
+        assert!(!diff.is_empty());
+        let keys = |list: &[EnvItem]| -> Vec<OsString> {
+            list.iter().map(|item| item.key.clone()).collect()
+        };
+        assert_eq!(keys(&diff.added), ["GITHUB_TOKEN", "HOME"]);
+        assert_eq!(keys(&diff.removed), ["AWS_ACCESS_KEY_ID", "INVOCATION_ID"]);
+        assert_eq!(diff.changed.len(), 3);
+        assert_eq!(diff.changed[0].key, "DATABASE_PASSWORD");
+
+        diff.redact(&RedactPolicy::default());
+        assert_eq!(diff.added[0].value, REDACTED);
+        assert_eq!(diff.added[1].value, "/home/alice");
+        assert_eq!(diff.removed[0].value, REDACTED);
+        assert_eq!(diff.changed[0].old_value, REDACTED);
+        assert_eq!(diff.changed[0].new_value, REDACTED);
+        assert_eq!(diff.changed[1].new_value, "en_US.UTF-8");
+
+        assert!(EnvDiff::new(&shell, &shell).is_empty());
+    }
 }
@@ -0,0 +1,174 @@
+// Copyright (c) 2023 Xu Shaohua <shaohua@biofan.org>. All rights reserved.
+// Use of this source is governed by General Public License that can be found
+// in the LICENSE file.
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::ops::RangeBounds;
+use std::path::Path;
+use std::time::SystemTime;
+
+use crate::base::system_root::SystemRoot;
+use crate::base::utmp;
+use crate::error::Error;
+
+/// Default path of failed login records.
+pub const BTMP_FILE: &str = "/var/log/btmp";
+
+/// Attempts from one host are flagged as brute-force at this count.
+pub const BRUTE_FORCE_THRESHOLD: usize = 5;
+
+/// Failed login attempt, like output of `lastb`.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FailedLogin {
+    pub user: String,
+
+    /// Remote host, empty for local login.
+    pub host: String,
+    pub tty: String,
+    pub time: SystemTime,
+
+    /// PID of process which recorded this attempt, like `sshd`.
+    pub pid: i32,
+}
+
+/// Failed login attempts grouped by source host or by user.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Attempts {
+    /// Source host or user name.
+    pub key: String,
+    pub count: usize,
+    pub first_time: SystemTime,
+    pub last_time: SystemTime,
+
+    /// Distinct users tried from this host, or hosts which tried this user.
+    pub related: Vec<String>,
+}
+
+impl Attempts {
+    #[must_use]
+    pub const fn is_brute_force(&self) -> bool {
+        self.count >= BRUTE_FORCE_THRESHOLD
+    }
+}
+
+/// Get failed login attempts in `/var/log/btmp`.
+///
+/// # Errors
+/// Returns error if failed to read or parse btmp file, it is only readable by root.
+pub fn get_list(root: &SystemRoot) -> Result<Vec<FailedLogin>, Error> {
+    get_list_from(root, BTMP_FILE)
+}
+
+/// Get failed login attempts in btmp file at `path`.
+///
+/// # Errors
+/// Returns error if failed to read or parse btmp file.
+pub fn get_list_from<P: AsRef<Path>>(
+    root: &SystemRoot,
+    path: P,
+) -> Result<Vec<FailedLogin>, Error> {
+    let records = utmp::read_records(root, path)?;
+    Ok(records
+        .into_iter()
+        .map(|record| FailedLogin {
+            user: record.user,
+            host: record.host,
+            tty: record.line,
+            time: record.time,
+            pid: record.pid,
+        })
+        .collect())
+}
+
+/// Group attempts within `window` by source host, most attempts first.
+#[must_use]
+pub fn group_by_host<R: RangeBounds<SystemTime>>(list: &[FailedLogin], window: R) -> Vec<Attempts> {
+    group_by(list, window, |item| (&item.host, &item.user))
+}
+
+/// Group attempts within `window` by user name, most attempts first.
+#[must_use]
+pub fn group_by_user<R: RangeBounds<SystemTime>>(list: &[FailedLogin], window: R) -> Vec<Attempts> {
+    group_by(list, window, |item| (&item.user, &item.host))
+}
+
+fn group_by<R, F>(list: &[FailedLogin], window: R, key: F) -> Vec<Attempts>
+where
+    R: RangeBounds<SystemTime>,
+    F: Fn(&FailedLogin) -> (&String, &String),
+{
+    let mut map: BTreeMap<&String, Attempts> = BTreeMap::new();
+    for item in list.iter().filter(|item| window.contains(&item.time)) {
+        let (key, related) = key(item);
+        let attempts = map.entry(key).or_insert_with(|| Attempts {
+            key: key.clone(),
+            count: 0,
+            first_time: item.time,
+            last_time: item.time,
+            related: Vec::new(),
+        });
+        attempts.count += 1;
+        attempts.first_time = attempts.first_time.min(item.time);
+        attempts.last_time = attempts.last_time.max(item.time);
+        if !attempts.related.contains(related) {
+            attempts.related.push(related.clone());
+        }
+    }
+
+    let mut list: Vec<Attempts> = map.into_values().collect();
+    list.sort_by_key(|attempts| Reverse(attempts.count));
+    list
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::{get_list, group_by_host, group_by_user};
+    use crate::base::system_root::SystemRoot;
+
+    #[test]
+    fn test_get_list() {
+        let list = get_list(&SystemRoot::fixture("laptop"));
+        assert!(list.is_ok());
+        let list = list.unwrap();
+        assert_eq!(list.len(), 7);
+        assert_eq!(list[0].user, "root");
+        assert_eq!(list[0].host, "203.0.113.5");
+        assert_eq!(list[0].tty, "ssh:notty");
+        assert_eq!(list[0].pid, 4001);
+        assert_eq!(list[6].tty, "tty1");
+        assert!(list[6].host.is_empty());
+    }
+
+    #[test]
+    fn test_group_by() {
+        let list = get_list(&SystemRoot::fixture("laptop")).unwrap();
+
+        let hosts = group_by_host(&list, ..);
+        assert_eq!(hosts.len(), 3);
+        assert_eq!(hosts[0].key, "203.0.113.5");
+        assert_eq!(hosts[0].count, 5);
+        assert_eq!(hosts[0].related, ["root", "admin", "oracle"]);
+        assert!(hosts[0].is_brute_force());
+        assert!(!hosts[1].is_brute_force());
+        assert_eq!(
+            hosts[0].last_time.duration_since(hosts[0].first_time).ok(),
+            Some(Duration::from_secs(4))
+        );
+
+        let users = group_by_user(&list, ..);
+        assert_eq!(users[0].key, "root");
+        assert_eq!(users[0].count, 3);
+        assert_eq!(users[0].related, ["203.0.113.5", "198.51.100.7"]);
+
+        // Only the local attempt is in the last hour.
+        let since = UNIX_EPOCH + Duration::from_secs(1_697_605_200 + 3600);
+        let hosts = group_by_host(&list, since..);
+        assert_eq!(hosts.len(), 1);
+        assert_eq!(hosts[0].related, ["shaohua"]);
+    }
+}
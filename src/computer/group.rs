@@ -2,18 +2,211 @@
 // Use of this source is governed by General Public License that can be found
 // in the LICENSE file.
 
+use std::os::raw::c_char;
+
+use crate::base::c_str::from_c_str;
+use crate::base::nss;
+use crate::base::system_root::SystemRoot;
 use crate::error::Error;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Group {
     pub name: String,
-    pub gid: i32,
+    pub gid: u32,
+
+    /// Supplementary members, users whose primary group is this group are not listed.
     pub members: Vec<String>,
+
+    /// Group administrators in `/etc/gshadow`, empty if it is not readable.
+    pub admins: Vec<String>,
 }
 
-/// # Errors
+/// Parse groups in `/etc/group`, and merge `/etc/gshadow` if it is readable.
 ///
-/// # Panics
-pub fn get_group_list() -> Result<Vec<Group>, Error> {
-    todo!()
+/// # Errors
+/// Returns error if failed to read or parse group file.
+pub fn get_group_list(root: &SystemRoot) -> Result<Vec<Group>, Error> {
+    const FILE: &str = "/etc/group";
+    let content = root.read_to_string(FILE)?;
+
+    let mut list = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('+') || line.starts_with('-') {
+            log::warn!("Ignore NIS entry in {FILE}: {line}");
+            continue;
+        }
+
+        let line_num = Some(index + 1);
+        let parts: Vec<&str> = line.split(':').collect();
+        if parts.len() != 4 {
+            return Err(Error::malformed(FILE, line_num, "columns", line));
+        }
+        let gid = parts[2]
+            .parse()
+            .map_err(|_err| Error::malformed(FILE, line_num, "gid", parts[2]))?;
+        list.push(Group {
+            name: parts[0].to_owned(),
+            gid,
+            members: split_names(parts[3]),
+            admins: Vec::new(),
+        });
+    }
+
+    // gshadow is only readable by root.
+    if let Err(err) = merge_gshadow(root, &mut list) {
+        log::info!("Skip gshadow: {err}");
+    }
+
+    Ok(list)
+}
+
+fn merge_gshadow(root: &SystemRoot, list: &mut [Group]) -> Result<(), Error> {
+    const FILE: &str = "/etc/gshadow";
+    let content = root.read_to_string(FILE)?;
+
+    for (index, line) in content.lines().enumerate() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let parts: Vec<&str> = line.split(':').collect();
+        if parts.len() != 4 {
+            return Err(Error::malformed(FILE, Some(index + 1), "columns", line));
+        }
+        if let Some(group) = list.iter_mut().find(|group| group.name == parts[0]) {
+            group.admins = split_names(parts[2]);
+            for member in split_names(parts[3]) {
+                if !group.members.contains(&member) {
+                    group.members.push(member);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn split_names(s: &str) -> Vec<String> {
+    s.split(',')
+        .filter(|name| !name.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+/// Enumerate groups of current system through NSS, so that groups in LDAP
+/// or SSSD are listed too.
+#[must_use]
+pub fn get_group_list_nss() -> Vec<Group> {
+    let mut list = Vec::new();
+
+    let _guard = nss::lock();
+    // SAFETY: Entries returned by getgrent() are valid until next call,
+    // they are copied before that. Enumeration is serialized by NSS lock.
+    unsafe {
+        libc::setgrent();
+        loop {
+            let entry = libc::getgrent();
+            if entry.is_null() {
+                break;
+            }
+            let entry = &*entry;
+            list.push(Group {
+                name: from_c_str(entry.gr_name),
+                gid: entry.gr_gid,
+                members: from_c_str_array(entry.gr_mem),
+                admins: Vec::new(),
+            });
+        }
+        libc::endgrent();
+    }
+
+    list
+}
+
+/// # Safety
+/// `ptr` shall be null or a null terminated array of valid C strings.
+unsafe fn from_c_str_array(ptr: *const *mut c_char) -> Vec<String> {
+    let mut list = Vec::new();
+    if ptr.is_null() {
+        return list;
+    }
+    let mut item = ptr;
+    while !(*item).is_null() {
+        list.push(from_c_str(*item));
+        item = item.add(1);
+    }
+    list
+}
+
+/// Find group with `gid`.
+#[must_use]
+pub fn find_by_gid(list: &[Group], gid: u32) -> Option<&Group> {
+    list.iter().find(|group| group.gid == gid)
+}
+
+/// Find group with `name`.
+#[must_use]
+pub fn find_by_name<'a>(list: &'a [Group], name: &str) -> Option<&'a Group> {
+    list.iter().find(|group| group.name == name)
+}
+
+/// Find groups which `user` is a supplementary member of.
+#[must_use]
+pub fn find_by_member<'a>(list: &'a [Group], user: &str) -> Vec<&'a Group> {
+    list.iter()
+        .filter(|group| group.members.iter().any(|member| member == user))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_by_gid, find_by_member, find_by_name, get_group_list, get_group_list_nss};
+    use crate::base::system_root::SystemRoot;
+    use crate::error::ErrorKind;
+
+    #[test]
+    fn test_get_group_list() {
+        let list = get_group_list(&SystemRoot::fixture("laptop"));
+        assert!(list.is_ok());
+        let list = list.unwrap();
+        assert_eq!(list.len(), 14);
+
+        let sudo = find_by_name(&list, "sudo").unwrap();
+        assert_eq!(sudo.gid, 27);
+        assert_eq!(sudo.members, ["shaohua"]);
+
+        // Members and admins from gshadow.
+        let docker = find_by_gid(&list, 998).unwrap();
+        assert_eq!(docker.members, ["shaohua", "ci", "deploy"]);
+        assert_eq!(docker.admins, ["shaohua"]);
+
+        let groups: Vec<&str> = find_by_member(&list, "shaohua")
+            .iter()
+            .map(|group| group.name.as_str())
+            .collect();
+        assert_eq!(groups, ["adm", "sudo", "plugdev", "docker"]);
+        assert_eq!(find_by_gid(&list, 65534).unwrap().name, "nogroup");
+    }
+
+    #[test]
+    fn test_get_group_list_malformed() {
+        let err = get_group_list(&SystemRoot::fixture("malformed")).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Malformed);
+        assert_eq!(
+            err.to_string(),
+            "Failed to parse `gid` in `/etc/group:2`, text: `twenty-seven`"
+        );
+    }
+
+    #[test]
+    fn test_get_group_list_nss() {
+        let list = get_group_list_nss();
+        assert_eq!(
+            find_by_gid(&list, 0).map(|group| group.name.as_str()),
+            Some("root")
+        );
+    }
 }
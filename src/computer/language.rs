@@ -2,7 +2,32 @@
 // Use of this source is governed by General Public License that can be found
 // in the LICENSE file.
 
-#[derive(Debug, Clone)]
+use std::env;
+use std::path::Path;
+
+use crate::base::system_root::SystemRoot;
+use crate::error::{Error, ErrorKind};
+
+/// Directory of locales generated by `localedef`.
+pub const LOCALE_DIR: &str = "/usr/lib/locale";
+
+/// Archive of locales generated by `locale-gen`.
+pub const LOCALE_ARCHIVE: &str = "/usr/lib/locale/locale-archive";
+
+/// Locale settings of systemd, `/etc/default/locale` is used on Debian.
+const LOCALE_CONF_FILES: &[&str] = &["/etc/locale.conf", "/etc/default/locale"];
+
+const ARCHIVE_MAGIC: u32 = 0xde02_0109;
+const IDENTIFICATION_MAGIC: u32 = 0x2003_1115 ^ LC_IDENTIFICATION;
+
+/// Index of `LC_IDENTIFICATION` in glibc locale categories.
+const LC_IDENTIFICATION: u32 = 12;
+
+/// Number of items in `LC_IDENTIFICATION`, `_NL_NUM_LC_IDENTIFICATION` in glibc.
+const IDENTIFICATION_ITEMS: usize = 16;
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Language {
     pub locale: String,
     pub title: String,
@@ -15,3 +40,361 @@ pub struct Language {
     pub date: String,
     pub code_set: String,
 }
+
+/// Where a locale variable is set.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LocaleSource {
+    /// Environment of current process.
+    Environment,
+
+    /// System wide config file, like `/etc/locale.conf`.
+    Config,
+}
+
+/// `LANG`, `LANGUAGE` or `LC_*` variable.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LocaleVar {
+    pub name: String,
+    pub value: String,
+    pub source: LocaleSource,
+}
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ActiveLocale {
+    pub vars: Vec<LocaleVar>,
+}
+
+impl ActiveLocale {
+    /// Get effective locale of `category`, like `LC_TIME`.
+    ///
+    /// `LC_ALL` overrides `category`, which overrides `LANG`. Variables in
+    /// environment override those in config file.
+    #[must_use]
+    pub fn get(&self, category: &str) -> Option<&str> {
+        ["LC_ALL", category, "LANG"].iter().find_map(|name| {
+            [LocaleSource::Environment, LocaleSource::Config]
+                .iter()
+                .find_map(|source| {
+                    self.vars
+                        .iter()
+                        .find(|var| {
+                            var.name == *name && var.source == *source && !var.value.is_empty()
+                        })
+                        .map(|var| var.value.as_str())
+                })
+        })
+    }
+}
+
+/// Get locales installed in `locale-archive` and in `/usr/lib/locale`, sorted by name.
+///
+/// Both are optional, like on musl systems or minimal containers.
+///
+/// # Errors
+/// Returns error if failed to read locale directory or to parse locale files.
+pub fn get_list(root: &SystemRoot) -> Result<Vec<Language>, Error> {
+    let mut list = match root.read(LOCALE_ARCHIVE) {
+        Ok(content) => parse_archive(Path::new(LOCALE_ARCHIVE), &content)?,
+        Err(err) if err.kind() == ErrorKind::NotPresent => Vec::new(),
+        Err(err) => return Err(err),
+    };
+
+    let dir = match root.read_dir(LOCALE_DIR) {
+        Ok(dir) => dir,
+        Err(err) if err.kind() == ErrorKind::NotPresent => Vec::new(),
+        Err(err) => return Err(err),
+    };
+    for filename in dir {
+        let path = Path::new(LOCALE_DIR)
+            .join(&filename)
+            .join("LC_IDENTIFICATION");
+        if !root.exists(&path) || list.iter().any(|lang| lang.locale == filename) {
+            continue;
+        }
+        let content = root.read(&path)?;
+        let mut lang = parse_identification(&path, &content)?;
+        lang.locale = filename;
+        list.push(lang);
+    }
+
+    list.sort_by(|a, b| a.locale.cmp(&b.locale));
+    Ok(list)
+}
+
+fn u32_at(path: &Path, content: &[u8], offset: usize) -> Result<u32, Error> {
+    content
+        .get(offset..offset + 4)
+        .map(|bytes| {
+            let mut buf = [0; 4];
+            buf.copy_from_slice(bytes);
+            u32::from_ne_bytes(buf)
+        })
+        .ok_or_else(|| Error::malformed(path, None, "offset", &offset.to_string()))
+}
+
+fn str_at(path: &Path, content: &[u8], offset: usize) -> Result<String, Error> {
+    let bytes = content
+        .get(offset..)
+        .ok_or_else(|| Error::malformed(path, None, "offset", &offset.to_string()))?;
+    let len = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    Ok(String::from_utf8_lossy(&bytes[..len]).into_owned())
+}
+
+/// Parse locale archive, see `struct locarhead` in glibc `locarchive.h`.
+fn parse_archive(path: &Path, content: &[u8]) -> Result<Vec<Language>, Error> {
+    let magic = u32_at(path, content, 0)?;
+    if magic != ARCHIVE_MAGIC {
+        return Err(Error::malformed(
+            path,
+            None,
+            "magic",
+            &format!("{magic:#x}"),
+        ));
+    }
+    let namehash_offset = u32_at(path, content, 8)? as usize;
+    let namehash_size = u32_at(path, content, 16)? as usize;
+
+    let mut list = Vec::new();
+    for index in 0..namehash_size {
+        // struct namehashent { hashval, name_offset, locrec_offset }
+        let entry = namehash_offset + index * 12;
+        let name_offset = u32_at(path, content, entry + 4)? as usize;
+        if name_offset == 0 {
+            continue;
+        }
+        let locrec_offset = u32_at(path, content, entry + 8)? as usize;
+
+        // struct locrecent { refs, record[__LC_LAST] { offset, len } }
+        let record = locrec_offset + 4 + LC_IDENTIFICATION as usize * 8;
+        let offset = u32_at(path, content, record)? as usize;
+        let len = u32_at(path, content, record + 4)? as usize;
+        let data = content
+            .get(offset..offset + len)
+            .ok_or_else(|| Error::malformed(path, None, "record", &offset.to_string()))?;
+
+        let mut lang = parse_identification(path, data)?;
+        lang.locale = str_at(path, content, name_offset)?;
+        list.push(lang);
+    }
+    Ok(list)
+}
+
+/// Parse binary `LC_IDENTIFICATION` record generated by `localedef`.
+///
+/// The record starts with magic number and number of items, followed by offset
+/// of each item, items are null terminated strings.
+fn parse_identification(path: &Path, content: &[u8]) -> Result<Language, Error> {
+    let magic = u32_at(path, content, 0)?;
+    if magic != IDENTIFICATION_MAGIC {
+        return Err(Error::malformed(
+            path,
+            None,
+            "magic",
+            &format!("{magic:#x}"),
+        ));
+    }
+    let count = u32_at(path, content, 4)? as usize;
+    if count < IDENTIFICATION_ITEMS {
+        return Err(Error::malformed(path, None, "count", &count.to_string()));
+    }
+    let item = |index: usize| -> Result<String, Error> {
+        let offset = u32_at(path, content, 8 + index * 4)? as usize;
+        str_at(path, content, offset)
+    };
+
+    // Order of items follows `_NL_IDENTIFICATION_*` in glibc `langinfo.h`.
+    Ok(Language {
+        locale: String::new(),
+        title: item(0)?,
+        source: item(1)?,
+        address: item(2)?,
+        email: item(4)?,
+        language: item(7)?,
+        territory: item(8)?,
+        revision: item(12)?,
+        date: item(13)?,
+        code_set: item(15)?,
+    })
+}
+
+/// Normalize locale name like glibc, `en_US.UTF-8` is stored as `en_US.utf8`.
+#[must_use]
+pub fn normalize_locale(name: &str) -> String {
+    let (name, modifier) = name
+        .split_once('@')
+        .map_or((name, None), |(n, m)| (n, Some(m)));
+    let mut locale = match name.split_once('.') {
+        Some((lang, code_set)) => {
+            let code_set: String = code_set
+                .chars()
+                .filter(char::is_ascii_alphanumeric)
+                .map(|c| c.to_ascii_lowercase())
+                .collect();
+            format!("{lang}.{code_set}")
+        }
+        None => name.to_owned(),
+    };
+    if let Some(modifier) = modifier {
+        locale.push('@');
+        locale.push_str(modifier);
+    }
+    locale
+}
+
+/// Find installed locale with `name`, like `en_US.UTF-8`.
+#[must_use]
+pub fn find_locale<'a>(list: &'a [Language], name: &str) -> Option<&'a Language> {
+    let name = normalize_locale(name);
+    list.iter()
+        .find(|lang| lang.locale == name || normalize_locale(&lang.locale) == name)
+}
+
+/// Returns true if `name` is built into glibc and needs no generated files.
+#[must_use]
+pub fn is_builtin_locale(name: &str) -> bool {
+    name == "C" || name == "POSIX"
+}
+
+/// Get active locale from environment of current process and `/etc/locale.conf`.
+///
+/// # Errors
+/// Returns error if failed to parse locale config file.
+pub fn get_active_locale(root: &SystemRoot) -> Result<ActiveLocale, Error> {
+    let vars = env::vars_os()
+        .filter_map(|(key, value)| Some((key.into_string().ok()?, value.into_string().ok()?)));
+    get_active_locale_from(root, vars)
+}
+
+/// Get active locale from environment variables `vars` and `/etc/locale.conf`.
+///
+/// # Errors
+/// Returns error if failed to parse locale config file.
+pub fn get_active_locale_from<I>(root: &SystemRoot, vars: I) -> Result<ActiveLocale, Error>
+where
+    I: IntoIterator<Item = (String, String)>,
+{
+    let mut active = ActiveLocale::default();
+    for (name, value) in vars {
+        if is_locale_var(&name) {
+            active.vars.push(LocaleVar {
+                name,
+                value,
+                source: LocaleSource::Environment,
+            });
+        }
+    }
+
+    for file in LOCALE_CONF_FILES {
+        let content = match root.read_to_string(file) {
+            Ok(content) => content,
+            Err(err) if err.kind() == ErrorKind::NotPresent => continue,
+            Err(err) => return Err(err),
+        };
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, value) = line
+                .split_once('=')
+                .ok_or_else(|| Error::malformed(file, Some(index + 1), "variable", line))?;
+            if is_locale_var(name) {
+                active.vars.push(LocaleVar {
+                    name: name.to_owned(),
+                    value: value.trim_matches('"').to_owned(),
+                    source: LocaleSource::Config,
+                });
+            }
+        }
+        break;
+    }
+
+    Ok(active)
+}
+
+fn is_locale_var(name: &str) -> bool {
+    name == "LANG" || name == "LANGUAGE" || name.starts_with("LC_")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    use super::{find_locale, get_active_locale_from, get_list, normalize_locale, LocaleSource};
+    use crate::base::system_root::SystemRoot;
+    use crate::error::ErrorKind;
+
+    #[test]
+    fn test_get_list() {
+        let list = get_list(&SystemRoot::fixture("laptop"));
+        assert!(list.is_ok());
+        let list = list.unwrap();
+        let names: Vec<&str> = list.iter().map(|lang| lang.locale.as_str()).collect();
+        assert_eq!(names, ["C.utf8", "en_US.utf8", "zh_CN.utf8"]);
+
+        let en = find_locale(&list, "en_US.UTF-8").unwrap();
+        assert_eq!(en.title, "English locale for the USA");
+        assert_eq!(en.language, "American English");
+        assert_eq!(en.territory, "United States");
+        assert_eq!(en.email, "bug-glibc-locales@gnu.org");
+        assert_eq!(en.code_set, "UTF-8");
+
+        let c = find_locale(&list, "C.UTF-8").unwrap();
+        assert_eq!(c.title, "C locale");
+        assert_eq!(c.revision, "2.1");
+        assert_eq!(c.date, "2022-01-30");
+        assert!(find_locale(&list, "de_DE.UTF-8").is_none());
+    }
+
+    #[test]
+    fn test_get_list_without_locale_dir() {
+        let dir = env::temp_dir().join(format!("xingtian-language-{}", process::id()));
+        assert!(fs::create_dir_all(&dir).is_ok());
+        let list = get_list(&SystemRoot::new(&dir));
+        let _ = fs::remove_dir_all(&dir);
+        assert!(list.is_ok());
+        assert!(list.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_get_list_malformed() {
+        let err = get_list(&SystemRoot::fixture("malformed")).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Malformed);
+        assert_eq!(
+            err.to_string(),
+            "Failed to parse `magic` in `/usr/lib/locale/C.utf8/LC_IDENTIFICATION`, text: `0x20031115`"
+        );
+    }
+
+    #[test]
+    fn test_normalize_locale() {
+        assert_eq!(normalize_locale("en_US.UTF-8"), "en_US.utf8");
+        assert_eq!(
+            normalize_locale("de_DE.ISO-8859-15@euro"),
+            "de_DE.iso885915@euro"
+        );
+        assert_eq!(normalize_locale("C"), "C");
+    }
+
+    #[test]
+    fn test_get_active_locale() {
+        let root = SystemRoot::fixture("laptop");
+        let vars = vec![
+            ("LC_TIME".to_owned(), "en_GB.UTF-8".to_owned()),
+            ("HOME".to_owned(), "/root".to_owned()),
+        ];
+        let active = get_active_locale_from(&root, vars).unwrap();
+        assert_eq!(active.vars.len(), 3);
+        assert_eq!(active.vars[1].source, LocaleSource::Config);
+        assert_eq!(active.get("LC_TIME"), Some("en_GB.UTF-8"));
+        assert_eq!(active.get("LC_MESSAGES"), Some("en_US.UTF-8"));
+
+        let active = get_active_locale_from(&root, Vec::new()).unwrap();
+        assert_eq!(active.get("LC_TIME"), Some("zh_CN.UTF-8"));
+    }
+}
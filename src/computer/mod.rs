@@ -4,8 +4,15 @@
 
 pub mod bootup;
 pub mod environment;
+pub mod failed_login;
 pub mod group;
 pub mod language;
+pub mod modprobe;
 pub mod module;
+pub mod module_graph;
+pub mod os;
+pub mod search_path;
+pub mod session;
 pub mod uptime;
+pub mod user;
 pub mod vendor;
@@ -0,0 +1,422 @@
+// Copyright (c) 2023 Xu Shaohua <shaohua@biofan.org>. All rights reserved.
+// Use of this source is governed by General Public License that can be found
+// in the LICENSE file.
+
+//! Parse config of `modprobe` in `modprobe.d` and of `systemd-modules-load`
+//! in `modules-load.d`.
+
+use std::path::{Path, PathBuf};
+
+use crate::base::system_root::SystemRoot;
+use crate::computer::module::Module;
+use crate::error::{Error, ErrorKind};
+
+/// Directories of modprobe config, a file overrides files with the same name
+/// in later directories.
+pub const MODPROBE_DIRS: &[&str] = &[
+    "/etc/modprobe.d",
+    "/run/modprobe.d",
+    "/usr/local/lib/modprobe.d",
+    "/lib/modprobe.d",
+    "/usr/lib/modprobe.d",
+];
+
+/// Directories of modules to load at boot, with the same precedence as [`MODPROBE_DIRS`].
+pub const MODULES_LOAD_DIRS: &[&str] = &[
+    "/etc/modules-load.d",
+    "/run/modules-load.d",
+    "/usr/local/lib/modules-load.d",
+    "/usr/lib/modules-load.d",
+];
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Directive {
+    Alias {
+        pattern: String,
+        module: String,
+    },
+    Blacklist(String),
+    Install {
+        module: String,
+        command: String,
+    },
+    Remove {
+        module: String,
+        command: String,
+    },
+    Options {
+        module: String,
+        options: Vec<String>,
+    },
+    Softdep {
+        module: String,
+        pre: Vec<String>,
+        post: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Entry {
+    pub directive: Directive,
+
+    /// Path of config file which defines this directive.
+    pub file: PathBuf,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LoadEntry {
+    pub module: String,
+    pub file: PathBuf,
+}
+
+/// Effective config, in the order modprobe reads it.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ModprobeConfig {
+    pub entries: Vec<Entry>,
+
+    /// Modules loaded at boot by `systemd-modules-load`.
+    pub load: Vec<LoadEntry>,
+}
+
+impl ModprobeConfig {
+    /// Get options of `module` in all `options` directives.
+    #[must_use]
+    pub fn options_of(&self, module: &str) -> Vec<&str> {
+        let module = normalize_name(module);
+        self.entries
+            .iter()
+            .filter_map(|entry| match &entry.directive {
+                Directive::Options {
+                    module: name,
+                    options,
+                } if *name == module => Some(options),
+                _ => None,
+            })
+            .flatten()
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Returns true if aliases of `module` are blacklisted.
+    #[must_use]
+    pub fn is_blacklisted(&self, module: &str) -> bool {
+        let module = normalize_name(module);
+        self.entries
+            .iter()
+            .any(|entry| matches!(&entry.directive, Directive::Blacklist(name) if *name == module))
+    }
+
+    /// Get command which replaces loading of `module`, the first one takes effect.
+    #[must_use]
+    pub fn install_of(&self, module: &str) -> Option<&str> {
+        let module = normalize_name(module);
+        self.entries
+            .iter()
+            .find_map(|entry| match &entry.directive {
+                Directive::Install {
+                    module: name,
+                    command,
+                } if *name == module => Some(command.as_str()),
+                _ => None,
+            })
+    }
+
+    /// Returns true if `module` is loaded at boot by `systemd-modules-load`.
+    #[must_use]
+    pub fn is_loaded_at_boot(&self, module: &str) -> bool {
+        let module = normalize_name(module);
+        self.load.iter().any(|entry| entry.module == module)
+    }
+
+    /// Set configured options and blacklist state of modules in `list`.
+    pub fn annotate(&self, list: &mut [Module]) {
+        for module in list {
+            module.configured_options = self
+                .options_of(&module.name)
+                .into_iter()
+                .map(str::to_owned)
+                .collect();
+            module.blacklisted = self.is_blacklisted(&module.name);
+        }
+    }
+}
+
+/// Module names are stored with underscores in kernel.
+fn normalize_name(name: &str) -> String {
+    name.replace('-', "_")
+}
+
+/// Get `*.conf` files in `dirs`, sorted by file name, a file in earlier directory
+/// overrides the one with the same name in later directories.
+fn config_files(root: &SystemRoot, dirs: &[&str]) -> Result<Vec<PathBuf>, Error> {
+    let mut files: Vec<(String, PathBuf)> = Vec::new();
+    for dir in dirs {
+        let names = match root.read_dir(dir) {
+            Ok(names) => names,
+            Err(err) if err.kind() == ErrorKind::NotPresent => continue,
+            Err(err) => return Err(err),
+        };
+        for name in names {
+            let path = Path::new(dir).join(&name);
+            let is_conf = path.extension().map_or(false, |ext| ext == "conf");
+            if is_conf && !files.iter().any(|(file, _)| *file == name) {
+                files.push((name, path));
+            }
+        }
+    }
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(files.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Join lines ending with backslash, returns line number of first line and content.
+fn logical_lines(content: &str) -> Vec<(usize, String)> {
+    let mut lines = Vec::new();
+    let mut current: Option<(usize, String)> = None;
+    for (index, line) in content.lines().enumerate() {
+        let (line_num, mut text) = current.take().unwrap_or((index + 1, String::new()));
+        if let Some(prefix) = line.strip_suffix('\\') {
+            text.push_str(prefix);
+            text.push(' ');
+            current = Some((line_num, text));
+        } else {
+            text.push_str(line);
+            lines.push((line_num, text));
+        }
+    }
+    if let Some(line) = current {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Split options by whitespace, whitespace in double quotes is kept.
+fn split_options(s: &str) -> Vec<String> {
+    let mut options = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in s.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                current.push(c);
+            }
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    options.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        options.push(current);
+    }
+    options
+}
+
+/// Parse a logical line in modprobe config, returns `None` for empty line and comment.
+///
+/// # Errors
+/// Returns error if command is unknown or module name is missing.
+pub fn parse_line(path: &Path, line_num: usize, line: &str) -> Result<Option<Directive>, Error> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    let malformed = |field| Error::malformed(path, Some(line_num), field, line);
+
+    let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let rest = rest.trim();
+    let (module, args) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    if module.is_empty() {
+        return Err(malformed("module"));
+    }
+    let name = normalize_name(module);
+    let args = args.trim();
+
+    let directive = match command {
+        "alias" => {
+            if args.is_empty() {
+                return Err(malformed("alias"));
+            }
+            Directive::Alias {
+                pattern: module.to_owned(),
+                module: normalize_name(args),
+            }
+        }
+        "blacklist" => Directive::Blacklist(name),
+        "install" => Directive::Install {
+            module: name,
+            command: args.to_owned(),
+        },
+        "remove" => Directive::Remove {
+            module: name,
+            command: args.to_owned(),
+        },
+        "options" => Directive::Options {
+            module: name,
+            options: split_options(args),
+        },
+        "softdep" => {
+            let mut pre = Vec::new();
+            let mut post = Vec::new();
+            let mut is_post = false;
+            for token in args.split_ascii_whitespace() {
+                match token {
+                    "pre:" => is_post = false,
+                    "post:" => is_post = true,
+                    dep if is_post => post.push(normalize_name(dep)),
+                    dep => pre.push(normalize_name(dep)),
+                }
+            }
+            Directive::Softdep {
+                module: name,
+                pre,
+                post,
+            }
+        }
+        _ => return Err(malformed("command")),
+    };
+    Ok(Some(directive))
+}
+
+/// Get effective config of `modprobe` and `systemd-modules-load`.
+///
+/// Like `modprobe`, lines which failed to parse, such as commands of newer
+/// kmod, are logged and skipped.
+///
+/// # Errors
+/// Returns error if failed to read config files.
+pub fn get_config(root: &SystemRoot) -> Result<ModprobeConfig, Error> {
+    let mut config = ModprobeConfig::default();
+
+    for file in config_files(root, MODPROBE_DIRS)? {
+        let content = root.read_to_string(&file)?;
+        for (line_num, line) in logical_lines(&content) {
+            match parse_line(&file, line_num, &line) {
+                Ok(Some(directive)) => config.entries.push(Entry {
+                    directive,
+                    file: file.clone(),
+                }),
+                Ok(None) => (),
+                Err(err) => log::warn!("Ignore line in modprobe config: {err}"),
+            }
+        }
+    }
+
+    for file in config_files(root, MODULES_LOAD_DIRS)? {
+        let content = root.read_to_string(&file)?;
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            config.load.push(LoadEntry {
+                module: normalize_name(line),
+                file: file.clone(),
+            });
+        }
+    }
+
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{get_config, parse_line, split_options, Directive};
+    use crate::base::system_root::SystemRoot;
+    use crate::computer::module;
+    use crate::error::ErrorKind;
+
+    #[test]
+    fn test_get_config() {
+        let config = get_config(&SystemRoot::fixture("laptop"));
+        assert!(config.is_ok());
+        let config = config.unwrap();
+        // `weakdep` of kmod 33 is skipped.
+        assert_eq!(config.entries.len(), 9);
+
+        assert_eq!(
+            config.options_of("snd-hda-intel"),
+            [
+                "power_save=1",
+                "model=\"dell-headset-multi\"",
+                "enable_msi=1"
+            ]
+        );
+        assert_eq!(config.options_of("dummy"), ["numdummies=0"]);
+        assert!(config.is_blacklisted("pcspkr"));
+        assert!(config.is_blacklisted("stp"));
+        // Blacklist in /usr/lib is overridden by file in /etc.
+        assert!(!config.is_blacklisted("snd_hda_intel"));
+        assert_eq!(config.install_of("pcspkr"), Some("/bin/true"));
+        assert_eq!(
+            config.entries[2].file,
+            Path::new("/etc/modprobe.d/alsa-base.conf")
+        );
+        assert!(matches!(
+            &config.entries[2].directive,
+            Directive::Softdep { pre, post, .. }
+                if pre == &["snd_hda_codec_hdmi"] && post == &["snd_hda_codec_realtek"]
+        ));
+
+        assert!(config.is_loaded_at_boot("bridge"));
+        assert!(config.is_loaded_at_boot("msr"));
+        assert!(!config.is_loaded_at_boot("br_netfilter"));
+    }
+
+    #[test]
+    fn test_annotate() {
+        let root = SystemRoot::fixture("laptop");
+        let modules = module::get_list(&root).unwrap();
+        let stp = &modules[0];
+        assert!(stp.blacklisted);
+        let snd = modules.iter().find(|m| m.name == "snd_hda_intel").unwrap();
+        assert!(!snd.blacklisted);
+        assert_eq!(snd.configured_options.len(), 3);
+        assert_eq!(
+            snd.ignored_options(),
+            ["model=\"dell-headset-multi\"", "enable_msi=1"]
+        );
+    }
+
+    #[test]
+    fn test_get_config_malformed() {
+        // Line with typo is skipped, other lines are kept.
+        let config = get_config(&SystemRoot::fixture("malformed"));
+        assert!(config.is_ok());
+        let config = config.unwrap();
+        assert!(config.is_blacklisted("pcspkr"));
+        assert!(!config.is_blacklisted("nouveau"));
+
+        let path = Path::new("/etc/modprobe.d/typo.conf");
+        let err = parse_line(path, 2, "blacklst nouveau").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Malformed);
+        assert_eq!(
+            err.to_string(),
+            "Failed to parse `command` in `/etc/modprobe.d/typo.conf:2`, text: `blacklst nouveau`"
+        );
+    }
+
+    #[test]
+    fn test_parse_line() {
+        let path = Path::new("/etc/modprobe.d/test.conf");
+        assert_eq!(parse_line(path, 1, "  # comment").unwrap(), None);
+        assert!(parse_line(path, 1, "options").is_err());
+        assert!(parse_line(path, 1, "alias pci:v00008086*").is_err());
+        assert_eq!(
+            parse_line(path, 1, "alias pci:v00008086* e1000e").unwrap(),
+            Some(Directive::Alias {
+                pattern: "pci:v00008086*".to_owned(),
+                module: "e1000e".to_owned(),
+            })
+        );
+        assert_eq!(split_options("a=1  b=\"x y\" c"), ["a=1", "b=\"x y\"", "c"]);
+    }
+}
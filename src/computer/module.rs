@@ -2,18 +2,107 @@
 // Use of this source is governed by General Public License that can be found
 // in the LICENSE file.
 
-use std::fs;
+use std::ffi::CString;
+use std::io;
+use std::os::unix::ffi::OsStringExt;
+use std::path::Path;
+use std::ptr;
 
-use crate::error::Error;
+use crate::base::c_str::from_c_str;
+use crate::base::system_root::SystemRoot;
+use crate::computer::modprobe;
+use crate::error::{Error, ErrorKind};
 
-#[derive(Debug, Clone)]
+const SYS_MODULE_DIR: &str = "/sys/module";
+pub(crate) const OSRELEASE_FILE: &str = "/proc/sys/kernel/osrelease";
+
+/// Load state of module in `/proc/modules`.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ModuleState {
+    #[default]
+    Live,
+    Loading,
+    Unloading,
+}
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Module {
     pub name: String,
     pub memory_bytes: usize,
     pub dependencies: Vec<String>,
+
+    /// Number of references, `None` if module can not be unloaded.
+    pub refcount: Option<i32>,
+    pub state: ModuleState,
+
+    /// Taint flags like `OE`, empty if not tainted.
+    pub taint: String,
+
+    /// Size of core and init sections in `/sys/module/<name>`.
+    pub core_size: Option<usize>,
+    pub init_size: Option<usize>,
+
+    /// Current parameter values, unreadable parameters are skipped.
+    pub parameters: Vec<ParamValue>,
+
+    /// Modules which hold a reference to this module.
+    pub holders: Vec<String>,
+
+    /// Built into kernel image, only listed in `/sys/module`.
+    pub builtin: bool,
+
+    /// Options in `modprobe.d`, like `power_save=1`.
+    pub configured_options: Vec<String>,
+
+    /// Blacklisted in `modprobe.d`.
+    pub blacklisted: bool,
 }
 
-#[derive(Debug, Clone)]
+impl Module {
+    /// Configured options whose value differs from current parameter value,
+    /// parameters not exposed in sysfs are skipped.
+    #[must_use]
+    pub fn ignored_options(&self) -> Vec<&str> {
+        self.configured_options
+            .iter()
+            .filter(|option| {
+                let (name, value) = option.split_once('=').unwrap_or((option, "1"));
+                self.parameters
+                    .iter()
+                    .find(|param| param.name == name)
+                    .map_or(false, |param| {
+                        !same_value(&param.value, value.trim_matches('"'))
+                    })
+            })
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+/// Compare parameter value in sysfs with configured one, bool parameters are shown as `Y`/`N`.
+fn same_value(current: &str, configured: &str) -> bool {
+    let to_bool = |s: &str| match s {
+        "Y" | "y" | "1" | "true" | "on" => Some(true),
+        "N" | "n" | "0" | "false" | "off" => Some(false),
+        _ => None,
+    };
+    if current == configured {
+        return true;
+    }
+    matches!((to_bool(current), to_bool(configured)), (Some(a), Some(b)) if a == b)
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ParamValue {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ModuleDetail {
     pub name: String,
     pub description: String,
@@ -29,78 +118,452 @@ pub struct ModuleDetail {
 
     pub signer: String,
     pub sig_key: String,
+
+    pub parameters: Vec<ModuleParam>,
+    pub aliases: Vec<String>,
+    pub firmware: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ModuleParam {
+    pub name: String,
+    pub param_type: String,
+    pub description: String,
 }
 
+/// Get loaded modules in `/proc/modules` with runtime state in `/sys/module`.
+///
+/// Built-in modules are not included, see [`get_builtin_list`].
+///
 /// # Errors
 /// Returns error if failed to read modules file or failed to parse content.
-pub fn get_list() -> Result<Vec<Module>, Error> {
-    const FILE: &str = "/proc/modules";
-    let content = fs::read_to_string(FILE).map_err(|err| Error::IoError(FILE, err))?;
-    let mut modules = Vec::new();
+pub fn get_list(root: &SystemRoot) -> Result<Vec<Module>, Error> {
+    let mut modules = read_proc_modules(root)?;
+    for module in &mut modules {
+        read_sys_module(root, module)?;
+    }
 
-    for line in content.lines() {
-        let mut iter = line.split_ascii_whitespace();
-        let name = iter
-            .next()
-            .ok_or_else(|| Error::ParseFile(FILE, "Failed to parse module name"))?
-            .to_string();
-        let memory_bytes: usize = iter
-            .next()
-            .ok_or_else(|| Error::ParseFile(FILE, "Failed to parse memory bytes"))?
-            .parse()
-            .map_err(|_err| Error::ParseFile(FILE, "Failed to parse memory bytes as integer"))?;
-        let _count: i32 = iter
-            .next()
-            .ok_or_else(|| Error::ParseFile(FILE, "Failed to parse count"))?
-            .parse()
-            .map_err(|_err| Error::ParseFile(FILE, "Failed to parse count as integer"))?;
-
-        let dependencies: Vec<String> = match iter
-            .next()
-            .ok_or_else(|| Error::ParseFile(FILE, "Failed to parse dependencies"))?
-        {
-            "-" => Vec::new(),
-            deps => deps
-                .split(',')
-                .filter(|s| !s.is_empty())
-                .map(str::to_owned)
-                .collect(),
-        };
+    match modprobe::get_config(root) {
+        Ok(config) => config.annotate(&mut modules),
+        Err(err) => log::warn!("Failed to get modprobe config: {err}"),
+    }
+
+    Ok(modules)
+}
+
+/// Get modules built into kernel, which are in `/sys/module` but not in `/proc/modules`.
+///
+/// Built-in modules are listed in `/sys/module` only if they have parameters
+/// or version. Returns empty list if sysfs is absent, like in containers.
+///
+/// # Errors
+/// Returns error if failed to read modules file or `/sys/module`.
+pub fn get_builtin_list(root: &SystemRoot) -> Result<Vec<Module>, Error> {
+    let loaded = read_proc_modules(root)?;
+    let names = match root.read_dir(SYS_MODULE_DIR) {
+        Ok(names) => names,
+        Err(err) if err.kind() == ErrorKind::NotPresent => Vec::new(),
+        Err(err) => return Err(err),
+    };
 
-        modules.push(Module {
+    let mut modules = Vec::new();
+    for name in names {
+        if loaded.iter().any(|module| module.name == name) {
+            continue;
+        }
+        let mut module = Module {
             name,
-            memory_bytes,
-            dependencies,
-        });
+            builtin: true,
+            ..Module::default()
+        };
+        read_sys_module(root, &mut module)?;
+        modules.push(module);
     }
-
     Ok(modules)
 }
 
+fn read_proc_modules(root: &SystemRoot) -> Result<Vec<Module>, Error> {
+    const FILE: &str = "/proc/modules";
+    let content = root.read_to_string(FILE)?;
+    content
+        .lines()
+        .enumerate()
+        .map(|(index, line)| parse_line(Some(index + 1), line))
+        .collect()
+}
+
+/// Parse a line in `/proc/modules`, like `nvidia 56623104 2 nvidia_modeset, Live 0x0 (POE)`.
+///
 /// # Errors
-/// Returns error if module not found or failed to read ko file.
+/// Returns error if line is malformed.
+pub fn parse_line(line_num: Option<usize>, line: &str) -> Result<Module, Error> {
+    const FILE: &str = "/proc/modules";
+    let mut iter = line.split_ascii_whitespace();
+    let name = iter
+        .next()
+        .ok_or_else(|| Error::malformed(FILE, line_num, "name", line))?
+        .to_string();
+    let memory_bytes = iter
+        .next()
+        .ok_or_else(|| Error::malformed(FILE, line_num, "memory_bytes", line))?;
+    let memory_bytes: usize = memory_bytes
+        .parse()
+        .map_err(|_err| Error::malformed(FILE, line_num, "memory_bytes", memory_bytes))?;
+    let count = iter
+        .next()
+        .ok_or_else(|| Error::malformed(FILE, line_num, "count", line))?;
+    let refcount = match count {
+        "-" => None,
+        count => Some(
+            count
+                .parse()
+                .map_err(|_err| Error::malformed(FILE, line_num, "count", count))?,
+        ),
+    };
+
+    let dependencies: Vec<String> = match iter
+        .next()
+        .ok_or_else(|| Error::malformed(FILE, line_num, "dependencies", line))?
+    {
+        "-" => Vec::new(),
+        deps => deps
+            .split(',')
+            .filter(|s| !s.is_empty())
+            .map(str::to_owned)
+            .collect(),
+    };
+
+    // State and taint flags are missing in kernels before 2.6.
+    let state = match iter.next() {
+        None | Some("Live") => ModuleState::Live,
+        Some("Loading") => ModuleState::Loading,
+        Some("Unloading") => ModuleState::Unloading,
+        Some(state) => return Err(Error::malformed(FILE, line_num, "state", state)),
+    };
+    let _address = iter.next();
+    let taint = iter
+        .next()
+        .map(|taint| {
+            taint
+                .trim_start_matches('(')
+                .trim_end_matches(')')
+                .to_owned()
+        })
+        .unwrap_or_default();
+
+    Ok(Module {
+        name,
+        memory_bytes,
+        dependencies,
+        refcount,
+        state,
+        taint,
+        ..Module::default()
+    })
+}
+
+/// Read sizes, parameters and holders in `/sys/module/<name>`, missing files are skipped.
+fn read_sys_module(root: &SystemRoot, module: &mut Module) -> Result<(), Error> {
+    let dir = Path::new(SYS_MODULE_DIR).join(&module.name);
+    module.core_size = read_size(root, &dir.join("coresize"))?;
+    module.init_size = read_size(root, &dir.join("initsize"))?;
+
+    let param_dir = dir.join("parameters");
+    for name in read_dir_or_empty(root, &param_dir)? {
+        match root.read_to_string(param_dir.join(&name)) {
+            Ok(value) => module.parameters.push(ParamValue {
+                name,
+                value: value.trim_end_matches('\n').to_owned(),
+            }),
+            // Some parameters are write-only or readable by root only.
+            Err(err) if err.kind() == ErrorKind::PermissionDenied => (),
+            Err(err) => return Err(err),
+        }
+    }
+
+    module.holders = read_dir_or_empty(root, &dir.join("holders"))?;
+    Ok(())
+}
+
+fn read_dir_or_empty(root: &SystemRoot, dir: &Path) -> Result<Vec<String>, Error> {
+    match root.read_dir(dir) {
+        Ok(names) => Ok(names),
+        Err(err) if err.kind() == ErrorKind::NotPresent => Ok(Vec::new()),
+        Err(err) => Err(err),
+    }
+}
+
+fn read_size(root: &SystemRoot, path: &Path) -> Result<Option<usize>, Error> {
+    match root.read_to_string(path) {
+        Ok(content) => {
+            let content = content.trim();
+            content
+                .parse()
+                .map(Some)
+                .map_err(|_err| Error::malformed(path, None, "size", content))
+        }
+        Err(err) if err.kind() == ErrorKind::NotPresent => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Get modinfo of module `name` in `/lib/modules/<release>` of running kernel
+/// in `root`, like `modinfo`.
 ///
-/// # Panics
-pub fn get_detail(_name: &str) -> Result<ModuleDetail, Error> {
-    todo!()
+/// # Errors
+/// Returns error if kernel release or modules directory is missing, module not
+/// found or failed to read ko file.
+pub fn get_detail(root: &SystemRoot, name: &str) -> Result<ModuleDetail, Error> {
+    let release = root.read_to_string(OSRELEASE_FILE)?;
+    let modules_dir = format!("/lib/modules/{}", release.trim());
+    if !root.is_dir(&modules_dir) {
+        return Err(Error::from_io(
+            modules_dir,
+            io::Error::from(io::ErrorKind::NotFound),
+        ));
+    }
+    let c_dir = CString::new(root.join(&modules_dir).into_os_string().into_vec())
+        .map_err(|_err| Error::NotFound(modules_dir.clone()))?;
+    let c_name = CString::new(name).map_err(|_err| Error::NotFound(name.to_owned()))?;
+
+    // SAFETY: Context, module and info list are released in reverse order, strings
+    // returned by libkmod are copied before that.
+    unsafe {
+        let ctx = kmod_sys::kmod_new(c_dir.as_ptr(), ptr::null());
+        if ctx.is_null() {
+            return Err(Error::Syscall {
+                name: "kmod_new",
+                source: io::Error::last_os_error(),
+            });
+        }
+
+        let mut module = ptr::null_mut();
+        let ret = kmod_sys::kmod_module_new_from_name(ctx, c_name.as_ptr(), &mut module);
+        if ret < 0 {
+            kmod_sys::kmod_unref(ctx);
+            return Err(Error::syscall("kmod_module_new_from_name", -ret));
+        }
+
+        let path = from_c_str(kmod_sys::kmod_module_get_path(module));
+        let size = kmod_sys::kmod_module_get_size(module);
+
+        let mut list = ptr::null_mut();
+        let ret = kmod_sys::kmod_module_get_info(module, &mut list);
+        let mut info = Vec::new();
+        let mut entry = list;
+        while !entry.is_null() {
+            info.push((
+                from_c_str(kmod_sys::kmod_module_info_get_key(entry)),
+                from_c_str(kmod_sys::kmod_module_info_get_value(entry)),
+            ));
+            entry = kmod_sys::kmod_list_next(list, entry);
+        }
+        kmod_sys::kmod_module_info_free_list(list);
+        kmod_sys::kmod_module_unref(module);
+        kmod_sys::kmod_unref(ctx);
+
+        // libkmod returns -ENOENT if module is neither in modules.dep nor built-in.
+        if ret == -libc::ENOENT {
+            return Err(Error::NotFound(name.to_owned()));
+        }
+        if ret < 0 {
+            return Err(Error::syscall("kmod_module_get_info", -ret));
+        }
+
+        let mut detail = parse_info(name, &info);
+        detail.path = path;
+        detail.memory_bytes = usize::try_from(size).unwrap_or_default();
+        Ok(detail)
+    }
+}
+
+/// Build module detail from modinfo key-value pairs, keys may repeat.
+fn parse_info(name: &str, info: &[(String, String)]) -> ModuleDetail {
+    let mut detail = ModuleDetail {
+        name: name.to_owned(),
+        ..ModuleDetail::default()
+    };
+
+    for (key, value) in info {
+        match key.as_str() {
+            "description" => detail.description.clone_from(value),
+            "vermagic" => detail.version_magic.clone_from(value),
+            "author" => {
+                if !detail.author.is_empty() {
+                    detail.author.push_str(", ");
+                }
+                detail.author.push_str(value);
+            }
+            "license" => detail.license.clone_from(value),
+            "signer" => detail.signer.clone_from(value),
+            "sig_key" => detail.sig_key.clone_from(value),
+            "depends" => {
+                detail.dependencies = value
+                    .split(',')
+                    .filter(|s| !s.is_empty())
+                    .map(str::to_owned)
+                    .collect();
+            }
+            "alias" => detail.aliases.push(value.clone()),
+            "firmware" => detail.firmware.push(value.clone()),
+            // Format is `name:description` and `name:type`.
+            "parm" | "parmtype" => {
+                let (param_name, text) = value.split_once(':').unwrap_or((value, ""));
+                let index = if let Some(index) =
+                    detail.parameters.iter().position(|p| p.name == param_name)
+                {
+                    index
+                } else {
+                    detail.parameters.push(ModuleParam {
+                        name: param_name.to_owned(),
+                        ..ModuleParam::default()
+                    });
+                    detail.parameters.len() - 1
+                };
+                let param = &mut detail.parameters[index];
+                if key == "parm" {
+                    param.description = text.trim().to_owned();
+                } else {
+                    param.param_type = text.to_owned();
+                }
+            }
+            _ => (),
+        }
+    }
+
+    detail
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{get_detail, get_list};
+    use super::{
+        get_builtin_list, get_detail, get_list, parse_info, parse_line, ModuleState, ParamValue,
+    };
+    use crate::base::system_root::SystemRoot;
+    use crate::error::ErrorKind;
 
     #[test]
     fn test_get_list() {
-        let module_list = get_list();
+        let module_list = get_list(&SystemRoot::default());
         assert!(module_list.is_ok());
         assert!(module_list.unwrap().len() > 10);
     }
 
     #[test]
+    fn test_get_list_fixture() {
+        let module_list = get_list(&SystemRoot::fixture("laptop"));
+        assert!(module_list.is_ok());
+        let module_list = module_list.unwrap();
+        assert_eq!(module_list.len(), 4);
+        assert!(module_list.iter().all(|module| !module.builtin));
+        assert_eq!(module_list[0].name, "stp");
+        assert_eq!(module_list[0].memory_bytes, 16384);
+        assert_eq!(module_list[0].dependencies, ["bridge"]);
+        assert_eq!(module_list[0].refcount, Some(1));
+        assert_eq!(module_list[0].state, ModuleState::Live);
+        assert_eq!(module_list[0].core_size, Some(16384));
+        assert_eq!(module_list[0].holders, ["bridge"]);
+        assert_eq!(module_list[1].dependencies, ["bridge", "stp"]);
+        assert_eq!(module_list[1].holders, ["bridge", "stp"]);
+        assert!(module_list[2].dependencies.is_empty());
+        assert!(module_list[2].parameters.is_empty());
+
+        let snd = &module_list[3];
+        assert!(!snd.builtin);
+        assert_eq!(snd.parameters.len(), 4);
+        assert_eq!(
+            snd.parameters[0],
+            ParamValue {
+                name: "enable_msi".to_owned(),
+                value: "-1".to_owned(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_get_builtin_list() {
+        let builtin_list = get_builtin_list(&SystemRoot::fixture("laptop"));
+        assert!(builtin_list.is_ok());
+        let builtin_list = builtin_list.unwrap();
+        assert_eq!(builtin_list.len(), 1);
+        let usbcore = &builtin_list[0];
+        assert_eq!(usbcore.name, "usbcore");
+        assert!(usbcore.builtin);
+        assert_eq!(usbcore.refcount, None);
+        assert_eq!(usbcore.core_size, None);
+        assert_eq!(usbcore.parameters[0].value, "2");
+    }
+
+    #[test]
+    fn test_parse_line() {
+        let module = parse_line(
+            Some(1),
+            "nvidia 56623104 2 nvidia_modeset, Unloading 0x0000000000000000 (POE)",
+        )
+        .unwrap();
+        assert_eq!(module.refcount, Some(2));
+        assert_eq!(module.state, ModuleState::Unloading);
+        assert_eq!(module.taint, "POE");
+
+        let module = parse_line(Some(1), "ext4 1069056 - - Loading 0x0").unwrap();
+        assert_eq!(module.refcount, None);
+        assert_eq!(module.state, ModuleState::Loading);
+        assert!(module.taint.is_empty());
+
+        assert!(parse_line(Some(1), "ext4 1069056 1 - Dead 0x0").is_err());
+    }
+
+    #[test]
+    #[ignore = "requires stp.ko of running kernel"]
     fn test_get_detail() {
         let name = "stp";
-        let module_detail = get_detail(name);
+        let module_detail = get_detail(&SystemRoot::default(), name);
         assert!(module_detail.is_ok());
         assert_eq!(module_detail.unwrap().dependencies, ["llc"]);
     }
+
+    #[test]
+    fn test_get_detail_fixture() {
+        // Kernel release is unknown.
+        let err = get_detail(&SystemRoot::fixture("partial"), "stp").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotPresent);
+        assert_eq!(
+            err.to_string(),
+            "File not present: `/proc/sys/kernel/osrelease`"
+        );
+    }
+
+    #[test]
+    fn test_parse_info() {
+        let info: Vec<(String, String)> = [
+            ("alias", "devname:fuse"),
+            ("alias", "char-major-10-229"),
+            ("license", "GPL"),
+            ("description", "Filesystem in Userspace"),
+            ("author", "Miklos Szeredi <miklos@szeredi.hu>"),
+            ("parm", "max_user_bgreq:Global limit for the maximum number of backgrounded requests an unprivileged user can set"),
+            ("parmtype", "max_user_bgreq:uint"),
+            ("parmtype", "max_user_congthresh:uint"),
+            ("depends", ""),
+            ("vermagic", "6.5.0-2-amd64 SMP preempt mod_unload modversions "),
+            ("firmware", "fuse.bin"),
+        ]
+        .iter()
+        .map(|(key, value)| ((*key).to_owned(), (*value).to_owned()))
+        .collect();
+
+        let detail = parse_info("fuse", &info);
+        assert_eq!(detail.name, "fuse");
+        assert_eq!(detail.description, "Filesystem in Userspace");
+        assert_eq!(detail.license, "GPL");
+        assert!(detail.dependencies.is_empty());
+        assert_eq!(detail.aliases, ["devname:fuse", "char-major-10-229"]);
+        assert_eq!(detail.firmware, ["fuse.bin"]);
+        assert_eq!(detail.parameters.len(), 2);
+        assert_eq!(detail.parameters[0].name, "max_user_bgreq");
+        assert_eq!(detail.parameters[0].param_type, "uint");
+        assert!(detail.parameters[0].description.starts_with("Global limit"));
+        assert!(detail.parameters[1].description.is_empty());
+    }
 }
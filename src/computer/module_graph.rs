@@ -0,0 +1,272 @@
+// Copyright (c) 2023 Xu Shaohua <shaohua@biofan.org>. All rights reserved.
+// Use of this source is governed by General Public License that can be found
+// in the LICENSE file.
+
+//! Dependency graph of kernel modules, built from `/proc/modules` and `modules.dep`.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::path::Path;
+
+use crate::base::system_root::SystemRoot;
+use crate::computer::module::{self, Module, OSRELEASE_FILE};
+use crate::error::{Error, ErrorKind};
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ModuleGraph {
+    /// Module name and names of modules it depends on.
+    pub dependencies: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl ModuleGraph {
+    /// Build graph from loaded modules.
+    ///
+    /// `Module::dependencies` of `/proc/modules` lists modules which use it.
+    #[must_use]
+    pub fn from_modules(list: &[Module]) -> Self {
+        let mut graph = Self::default();
+        for module in list {
+            graph.add_node(&module.name);
+            for user in &module.dependencies {
+                graph.add_edge(user, &module.name);
+            }
+        }
+        graph
+    }
+
+    /// Build graph of all modules of running kernel in `modules.dep`.
+    ///
+    /// # Errors
+    /// Returns error if failed to read kernel release or to parse `modules.dep`.
+    pub fn from_modules_dep(root: &SystemRoot) -> Result<Self, Error> {
+        let path = modules_dep_path(root)?;
+        let content = root.read_to_string(&path)?;
+
+        let mut graph = Self::default();
+        for (index, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (module, deps) = line
+                .split_once(':')
+                .ok_or_else(|| Error::malformed(&path, Some(index + 1), "module", line))?;
+            let name = module_name(module);
+            graph.add_node(&name);
+            for dep in deps.split_ascii_whitespace() {
+                graph.add_edge(&name, &module_name(dep));
+            }
+        }
+        Ok(graph)
+    }
+
+    fn add_node(&mut self, name: &str) {
+        self.dependencies.entry(name.to_owned()).or_default();
+    }
+
+    fn add_edge(&mut self, from: &str, to: &str) {
+        self.add_node(to);
+        self.dependencies
+            .entry(from.to_owned())
+            .or_default()
+            .insert(to.to_owned());
+    }
+
+    /// Merge dependencies of modules in `self` from `other`, modules only in
+    /// `other` are added if they are dependencies.
+    pub fn merge(&mut self, other: &Self) {
+        let names: Vec<String> = self.dependencies.keys().cloned().collect();
+        for name in names {
+            if let Some(deps) = other.dependencies.get(&name) {
+                for dep in deps {
+                    self.add_edge(&name, dep);
+                }
+            }
+        }
+    }
+
+    /// Modules which `name` depends on directly.
+    #[must_use]
+    pub fn dependencies_of(&self, name: &str) -> Vec<&str> {
+        self.dependencies
+            .get(name)
+            .map(|deps| deps.iter().map(String::as_str).collect())
+            .unwrap_or_default()
+    }
+
+    /// Modules which depend on `name` directly.
+    #[must_use]
+    pub fn dependents_of(&self, name: &str) -> Vec<&str> {
+        self.dependencies
+            .iter()
+            .filter(|(_, deps)| deps.contains(name))
+            .map(|(module, _)| module.as_str())
+            .collect()
+    }
+
+    /// All modules which `name` depends on, directly or indirectly.
+    #[must_use]
+    pub fn closure(&self, name: &str) -> Vec<&str> {
+        let mut visited = BTreeSet::new();
+        self.visit(name, &mut visited, &|graph, name| {
+            graph.dependencies_of(name)
+        });
+        visited.remove(name);
+        visited.into_iter().collect()
+    }
+
+    /// All modules which depend on `name`, directly or indirectly.
+    #[must_use]
+    pub fn reverse_closure(&self, name: &str) -> Vec<&str> {
+        let mut visited = BTreeSet::new();
+        self.visit(name, &mut visited, &|graph, name| graph.dependents_of(name));
+        visited.remove(name);
+        visited.into_iter().collect()
+    }
+
+    fn visit<'a, F>(&'a self, name: &str, visited: &mut BTreeSet<&'a str>, next: &F)
+    where
+        F: Fn(&'a Self, &str) -> Vec<&'a str>,
+    {
+        for item in next(self, name) {
+            if visited.insert(item) {
+                self.visit(item, visited, next);
+            }
+        }
+    }
+
+    /// Modules to unload before `name` can be unloaded, in order, `name` is the last one.
+    #[must_use]
+    pub fn unload_order(&self, name: &str) -> Vec<&str> {
+        let mut order = Vec::new();
+        let mut visited = BTreeSet::new();
+        if let Some((key, _)) = self.dependencies.get_key_value(name) {
+            self.post_order(key, &mut visited, &mut order);
+        }
+        order
+    }
+
+    fn post_order<'a>(
+        &'a self,
+        name: &'a str,
+        visited: &mut BTreeSet<&'a str>,
+        order: &mut Vec<&'a str>,
+    ) {
+        if !visited.insert(name) {
+            return;
+        }
+        for dependent in self.dependents_of(name) {
+            self.post_order(dependent, visited, order);
+        }
+        order.push(name);
+    }
+
+    /// Export graph in Graphviz DOT format, edges point to dependencies.
+    #[must_use]
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph modules {\n");
+        for (name, deps) in &self.dependencies {
+            if deps.is_empty() {
+                let _ = writeln!(dot, "  \"{name}\";");
+            }
+            for dep in deps {
+                let _ = writeln!(dot, "  \"{name}\" -> \"{dep}\";");
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// Path of `modules.dep` of running kernel in target system.
+fn modules_dep_path(root: &SystemRoot) -> Result<String, Error> {
+    let release = root.read_to_string(OSRELEASE_FILE)?;
+    Ok(format!("/lib/modules/{}/modules.dep", release.trim()))
+}
+
+/// Convert path in `modules.dep` to module name, like `kernel/sound/core/snd-pcm.ko.xz`
+/// to `snd_pcm`.
+fn module_name(path: &str) -> String {
+    let filename = Path::new(path.trim())
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    let name = filename.split(".ko").next().unwrap_or_default();
+    name.replace('-', "_")
+}
+
+/// Get graph of loaded modules, with dependencies in `modules.dep` if it exists.
+///
+/// # Errors
+/// Returns error if failed to read loaded modules or to parse `modules.dep`.
+pub fn get_graph(root: &SystemRoot) -> Result<ModuleGraph, Error> {
+    let list = module::get_list(root)?;
+    let mut graph = ModuleGraph::from_modules(&list);
+    match ModuleGraph::from_modules_dep(root) {
+        Ok(all) => graph.merge(&all),
+        Err(err) if err.kind() == ErrorKind::NotPresent => {
+            log::info!("Skip modules.dep: {err}");
+        }
+        Err(err) => return Err(err),
+    }
+    Ok(graph)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{get_graph, module_name, ModuleGraph};
+    use crate::base::system_root::SystemRoot;
+    use crate::error::ErrorKind;
+
+    #[test]
+    fn test_module_name() {
+        assert_eq!(module_name("kernel/sound/core/snd-pcm.ko.xz"), "snd_pcm");
+        assert_eq!(module_name(" kernel/net/802/stp.ko"), "stp");
+    }
+
+    #[test]
+    fn test_get_graph() {
+        let graph = get_graph(&SystemRoot::fixture("laptop"));
+        assert!(graph.is_ok());
+        let graph = graph.unwrap();
+
+        assert_eq!(graph.dependencies_of("bridge"), ["llc", "stp"]);
+        assert_eq!(graph.dependents_of("llc"), ["bridge", "stp"]);
+        assert_eq!(graph.dependents_of("bridge"), Vec::<&str>::new());
+        // br_netfilter is not loaded.
+        assert!(!graph.dependencies.contains_key("br_netfilter"));
+
+        assert_eq!(
+            graph.closure("snd_hda_intel"),
+            [
+                "snd",
+                "snd_hda_codec",
+                "snd_hda_core",
+                "snd_pcm",
+                "soundcore"
+            ]
+        );
+        assert_eq!(graph.reverse_closure("llc"), ["bridge", "stp"]);
+        assert_eq!(graph.unload_order("llc"), ["bridge", "stp", "llc"]);
+        assert!(graph.unload_order("nvidia").is_empty());
+
+        let dot = graph.to_dot();
+        assert!(dot.starts_with("digraph modules {\n  \"bridge\" -> \"llc\";\n"));
+        assert!(dot.contains("  \"llc\";\n"));
+    }
+
+    #[test]
+    fn test_from_modules_dep() {
+        let graph = ModuleGraph::from_modules_dep(&SystemRoot::fixture("laptop")).unwrap();
+        assert_eq!(graph.dependencies.len(), 10);
+        assert_eq!(graph.reverse_closure("bridge"), ["br_netfilter"]);
+
+        let err = ModuleGraph::from_modules_dep(&SystemRoot::fixture("malformed")).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Malformed);
+        assert_eq!(
+            err.to_string(),
+            "Failed to parse `module` in `/lib/modules/6.5.0-2-amd64/modules.dep:2`, \
+             text: `kernel/net/802/stp.ko.xz kernel/net/llc/llc.ko.xz`"
+        );
+    }
+}
@@ -0,0 +1,397 @@
+// Copyright (c) 2023 Xu Shaohua <shaohua@biofan.org>. All rights reserved.
+// Use of this source is governed by General Public License that can be found
+// in the LICENSE file.
+
+use std::path::PathBuf;
+
+use crate::base::system_root::SystemRoot;
+use crate::computer::environment::{self, EnvItem};
+use crate::error::{Error, ErrorKind};
+
+/// Files of distribution info, in order of precedence.
+const OS_RELEASE_FILES: &[&str] = &["/etc/os-release", "/usr/lib/os-release"];
+const LSB_RELEASE_FILE: &str = "/etc/lsb-release";
+const HOSTNAME_FILE: &str = "/etc/hostname";
+const RESOLV_FILE: &str = "/etc/resolv.conf";
+
+/// Directory of kernel info in procfs, same as fields of `uname()`.
+const KERNEL_DIR: &str = "/proc/sys/kernel";
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OsRelease {
+    pub name: String,
+    pub pretty_name: String,
+    pub id: String,
+    pub id_like: Vec<String>,
+    pub version: String,
+    pub version_id: String,
+    pub version_codename: String,
+    pub variant: String,
+    pub variant_id: String,
+
+    /// File which these fields are read from.
+    pub source: PathBuf,
+}
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Kernel {
+    pub sysname: String,
+    pub nodename: String,
+    pub release: String,
+    pub version: String,
+    pub machine: String,
+
+    /// NIS domain name, `(none)` if it is not set.
+    pub domainname: String,
+}
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Desktop {
+    /// Desktop environment in `XDG_CURRENT_DESKTOP`, like `GNOME`.
+    pub desktop: String,
+
+    /// Session name in `DESKTOP_SESSION`.
+    pub session: String,
+
+    /// Session type in `XDG_SESSION_TYPE`, like `wayland` or `x11`.
+    pub session_type: String,
+}
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OperatingSystem {
+    pub release: OsRelease,
+    pub kernel: Kernel,
+    pub hostname: String,
+    pub domain: String,
+    pub desktop: Desktop,
+}
+
+/// Parse `KEY=value` lines in os-release format, value may be quoted.
+fn parse_key_values(path: &str, content: &str) -> Result<Vec<(String, String)>, Error> {
+    let mut list = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| Error::malformed(path, Some(index + 1), "variable", line))?;
+        list.push((key.to_owned(), unquote(value)));
+    }
+    Ok(list)
+}
+
+/// Remove quotes and backslash escapes of shell string.
+fn unquote(value: &str) -> String {
+    let quoted = value.len() >= 2
+        && (value.starts_with('"') && value.ends_with('"')
+            || value.starts_with('\'') && value.ends_with('\''));
+    if !quoted {
+        return value.to_owned();
+    }
+    let inner = &value[1..value.len() - 1];
+    let mut result = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            if let Some(next) = chars.next() {
+                result.push(next);
+            }
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+/// Get distribution info in `/etc/os-release`, `/usr/lib/os-release` or `/etc/lsb-release`.
+///
+/// # Errors
+/// Returns error if none of these files exists or failed to parse file.
+pub fn get_os_release(root: &SystemRoot) -> Result<OsRelease, Error> {
+    for file in OS_RELEASE_FILES {
+        let content = match root.read_to_string(file) {
+            Ok(content) => content,
+            Err(err) if err.kind() == ErrorKind::NotPresent => continue,
+            Err(err) => return Err(err),
+        };
+        let mut release = OsRelease {
+            // Default values in os-release(5).
+            name: "Linux".to_owned(),
+            pretty_name: "Linux".to_owned(),
+            id: "linux".to_owned(),
+            source: PathBuf::from(file),
+            ..OsRelease::default()
+        };
+        for (key, value) in parse_key_values(file, &content)? {
+            match key.as_str() {
+                "NAME" => release.name = value,
+                "PRETTY_NAME" => release.pretty_name = value,
+                "ID" => release.id = value,
+                "ID_LIKE" => {
+                    release.id_like = value.split_ascii_whitespace().map(str::to_owned).collect();
+                }
+                "VERSION" => release.version = value,
+                "VERSION_ID" => release.version_id = value,
+                "VERSION_CODENAME" => release.version_codename = value,
+                "VARIANT" => release.variant = value,
+                "VARIANT_ID" => release.variant_id = value,
+                _ => (),
+            }
+        }
+        return Ok(release);
+    }
+
+    let content = root.read_to_string(LSB_RELEASE_FILE)?;
+    let mut release = OsRelease {
+        source: PathBuf::from(LSB_RELEASE_FILE),
+        ..OsRelease::default()
+    };
+    for (key, value) in parse_key_values(LSB_RELEASE_FILE, &content)? {
+        match key.as_str() {
+            "DISTRIB_ID" => {
+                release.id = value.to_lowercase();
+                release.name = value;
+            }
+            "DISTRIB_RELEASE" => release.version_id = value,
+            "DISTRIB_CODENAME" => release.version_codename = value,
+            "DISTRIB_DESCRIPTION" => release.pretty_name = value,
+            _ => (),
+        }
+    }
+    Ok(release)
+}
+
+/// Get kernel info in `/proc/sys/kernel` of `root`.
+///
+/// `arch` is only present since Linux 6.1, machine of live root is read with
+/// `uname()` if it is missing. Fields of other missing files are left empty.
+///
+/// # Errors
+/// Returns error if failed to read these files or syscall failed.
+pub fn get_kernel(root: &SystemRoot) -> Result<Kernel, Error> {
+    let read = |name: &str| match root.read_to_string(format!("{KERNEL_DIR}/{name}")) {
+        Ok(content) => Ok(content.trim_end().to_owned()),
+        Err(err) if err.kind() == ErrorKind::NotPresent => Ok(String::new()),
+        Err(err) => Err(err),
+    };
+    let mut kernel = Kernel {
+        sysname: read("ostype")?,
+        nodename: read("hostname")?,
+        release: read("osrelease")?,
+        version: read("version")?,
+        machine: read("arch")?,
+        domainname: read("domainname")?,
+    };
+    if kernel.machine.is_empty() && root.is_live() {
+        kernel.machine = uname()?.machine;
+    }
+    Ok(kernel)
+}
+
+/// Get kernel info of current system with `uname()`.
+///
+/// # Errors
+/// Returns error if syscall failed.
+pub fn uname() -> Result<Kernel, Error> {
+    let mut buf = nc::utsname_t::default();
+    // SAFETY: `buf` is a valid utsname struct which lives until syscall returns.
+    unsafe { nc::uname(&mut buf) }.map_err(|errno| Error::syscall("uname", errno))?;
+
+    let to_string = |field: &[u8]| {
+        let len = field.iter().position(|b| *b == 0).unwrap_or(field.len());
+        String::from_utf8_lossy(&field[..len]).into_owned()
+    };
+    Ok(Kernel {
+        sysname: to_string(&buf.sysname),
+        nodename: to_string(&buf.nodename),
+        release: to_string(&buf.release),
+        version: to_string(&buf.version),
+        machine: to_string(&buf.machine),
+        domainname: to_string(&buf.domainname),
+    })
+}
+
+/// Get desktop environment and session type in environment variables.
+#[must_use]
+pub fn get_desktop(env: &[EnvItem]) -> Desktop {
+    let get = |key: &str| {
+        env.iter()
+            .find(|item| item.key == key)
+            .map(|item| item.value.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    };
+    Desktop {
+        desktop: get("XDG_CURRENT_DESKTOP"),
+        session: get("DESKTOP_SESSION"),
+        session_type: get("XDG_SESSION_TYPE"),
+    }
+}
+
+/// Get domain of host, from hostname if it is fully qualified, or `domain` and
+/// `search` in `/etc/resolv.conf`.
+fn get_domain(root: &SystemRoot, hostname: &str) -> String {
+    if let Some((_, domain)) = hostname.split_once('.') {
+        return domain.to_owned();
+    }
+    let content = match root.read_to_string(RESOLV_FILE) {
+        Ok(content) => content,
+        Err(err) => {
+            log::info!("Skip domain: {err}");
+            return String::new();
+        }
+    };
+    // The last `domain` or `search` line takes effect, see resolv.conf(5).
+    content
+        .lines()
+        .rev()
+        .find_map(|line| {
+            let mut iter = line.split_ascii_whitespace();
+            match iter.next() {
+                Some("domain" | "search") => iter.next(),
+                _ => None,
+            }
+        })
+        .unwrap_or_default()
+        .to_owned()
+}
+
+/// Get distribution, kernel, host name and desktop environment.
+///
+/// Desktop environment is read from environment of current process, so it is
+/// empty unless `root` is live.
+///
+/// # Errors
+/// Returns error if failed to get distribution or kernel info.
+pub fn get_os(root: &SystemRoot) -> Result<OperatingSystem, Error> {
+    let release = get_os_release(root)?;
+    let kernel = get_kernel(root)?;
+    let hostname = match root.read_to_string(HOSTNAME_FILE) {
+        Ok(content) => content.trim().to_owned(),
+        Err(err) if err.kind() == ErrorKind::NotPresent => kernel.nodename.clone(),
+        Err(err) => return Err(err),
+    };
+    let domain = get_domain(root, &hostname);
+    let desktop = if root.is_live() {
+        get_desktop(&environment::get_environment())
+    } else {
+        Desktop::default()
+    };
+
+    Ok(OperatingSystem {
+        release,
+        kernel,
+        hostname,
+        domain,
+        desktop,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsString;
+    use std::path::Path;
+
+    use super::{get_desktop, get_kernel, get_os, get_os_release, uname, unquote};
+    use crate::base::system_root::SystemRoot;
+    use crate::computer::environment::EnvItem;
+    use crate::error::ErrorKind;
+
+    #[test]
+    fn test_get_os_release() {
+        let release = get_os_release(&SystemRoot::fixture("laptop"));
+        assert!(release.is_ok());
+        let release = release.unwrap();
+        assert_eq!(release.name, "Debian GNU/Linux");
+        assert_eq!(release.pretty_name, "Debian GNU/Linux trixie/sid");
+        assert_eq!(release.id, "debian");
+        assert!(release.id_like.is_empty());
+        assert!(release.version_id.is_empty());
+        assert_eq!(release.version_codename, "trixie");
+        assert_eq!(release.source, Path::new("/etc/os-release"));
+    }
+
+    #[test]
+    fn test_get_os_release_lsb() {
+        let release = get_os_release(&SystemRoot::fixture("partial")).unwrap();
+        assert_eq!(release.name, "Ubuntu");
+        assert_eq!(release.id, "ubuntu");
+        assert_eq!(release.version_id, "22.04");
+        assert_eq!(release.pretty_name, "Ubuntu 22.04.3 LTS");
+        assert_eq!(release.source, Path::new("/etc/lsb-release"));
+    }
+
+    #[test]
+    fn test_get_os_release_malformed() {
+        let err = get_os_release(&SystemRoot::fixture("malformed")).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Malformed);
+        assert_eq!(
+            err.to_string(),
+            "Failed to parse `variable` in `/usr/lib/os-release:2`, text: `VERSION_ID 39`"
+        );
+    }
+
+    #[test]
+    fn test_unquote() {
+        assert_eq!(unquote("\"Fedora Linux\""), "Fedora Linux");
+        assert_eq!(unquote("'a \\\"b\\\"'"), "a \"b\"");
+        assert_eq!(unquote("\""), "\"");
+        assert_eq!(unquote("debian"), "debian");
+    }
+
+    #[test]
+    fn test_get_desktop() {
+        let env: Vec<EnvItem> = [
+            ("XDG_CURRENT_DESKTOP", "GNOME"),
+            ("XDG_SESSION_TYPE", "wayland"),
+        ]
+        .iter()
+        .map(|(key, value)| EnvItem {
+            key: OsString::from(key),
+            value: OsString::from(value),
+        })
+        .collect();
+        let desktop = get_desktop(&env);
+        assert_eq!(desktop.desktop, "GNOME");
+        assert_eq!(desktop.session_type, "wayland");
+        assert!(desktop.session.is_empty());
+    }
+
+    #[test]
+    fn test_get_kernel() {
+        let kernel = uname();
+        assert!(kernel.is_ok());
+        assert_eq!(kernel.unwrap().sysname, "Linux");
+
+        let kernel = get_kernel(&SystemRoot::fixture("laptop")).unwrap();
+        assert_eq!(kernel.sysname, "Linux");
+        assert_eq!(kernel.nodename, "laptop");
+        assert_eq!(kernel.release, "6.5.0-2-amd64");
+        assert_eq!(
+            kernel.version,
+            "#1 SMP PREEMPT_DYNAMIC Debian 6.5.6-1 (2023-10-07)"
+        );
+        assert_eq!(kernel.machine, "x86_64");
+        assert_eq!(kernel.domainname, "(none)");
+
+        // Machine of fixture is not read from current system.
+        let kernel = get_kernel(&SystemRoot::fixture("partial")).unwrap();
+        assert!(kernel.release.is_empty());
+        assert!(kernel.machine.is_empty());
+    }
+
+    #[test]
+    fn test_get_os() {
+        let os = get_os(&SystemRoot::fixture("laptop")).unwrap();
+        assert_eq!(os.kernel.release, "6.5.0-2-amd64");
+        assert_eq!(os.hostname, "laptop");
+        assert_eq!(os.domain, "lan");
+        // Environment of current process does not describe fixture.
+        assert!(os.desktop.desktop.is_empty());
+    }
+}
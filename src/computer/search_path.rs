@@ -0,0 +1,343 @@
+// Copyright (c) 2023 Xu Shaohua <shaohua@biofan.org>. All rights reserved.
+// Use of this source is governed by General Public License that can be found
+// in the LICENSE file.
+
+//! Analyse search path variables, like `PATH`, `LD_LIBRARY_PATH` and `LD_PRELOAD`.
+
+use std::fmt;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use crate::base::system_root::SystemRoot;
+use crate::computer::environment::EnvItem;
+
+/// Variables which hold a list of directories, separated by `:`.
+const DIR_LIST_VARS: &[&str] = &["PATH", "LD_LIBRARY_PATH", "MANPATH"];
+const PRELOAD_VAR: &str = "LD_PRELOAD";
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Issue {
+    /// Entry does not exist.
+    Missing,
+
+    /// Same entry appears earlier in the list, this one takes no effect.
+    Duplicate,
+
+    /// Anyone can write to entry, and plant programs or libraries there.
+    WorldWritable,
+
+    /// Entry is relative to current directory, including empty entry.
+    Relative,
+
+    /// Entry is expected to be a directory, but it is a file.
+    NotDirectory,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::Missing => "missing",
+            Self::Duplicate => "duplicate",
+            Self::WorldWritable => "world-writable",
+            Self::Relative => "relative",
+            Self::NotDirectory => "not a directory",
+        };
+        f.write_str(s)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PathEntry {
+    /// Entry as written in variable.
+    pub path: PathBuf,
+    pub issues: Vec<Issue>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PathVar {
+    pub key: String,
+
+    /// Entries in order of precedence.
+    pub entries: Vec<PathEntry>,
+}
+
+impl PathVar {
+    /// Parse and check entries of variable `key`.
+    ///
+    /// Returns `None` if `key` is not a search path variable.
+    #[must_use]
+    pub fn new(root: &SystemRoot, key: &str, value: &str) -> Option<Self> {
+        let entries = if key == PRELOAD_VAR {
+            // Libraries in LD_PRELOAD are separated by colons or spaces, see ld.so(8).
+            value
+                .split(|c: char| c == ':' || c.is_ascii_whitespace())
+                .filter(|item| !item.is_empty())
+                .map(|item| check_preload(root, item))
+                .collect()
+        } else if is_dir_list(key) {
+            let mut entries: Vec<PathEntry> = Vec::new();
+            for item in value.split(':') {
+                // Empty entry in MANPATH means default search path, see manpath(5).
+                if item.is_empty() && key == "MANPATH" {
+                    continue;
+                }
+                let mut entry = check_dir(root, item);
+                if !item.is_empty()
+                    && entries
+                        .iter()
+                        .any(|prev| same_path(&prev.path, &entry.path))
+                {
+                    entry.issues.push(Issue::Duplicate);
+                }
+                entries.push(entry);
+            }
+            entries
+        } else if is_single_dir(key) {
+            vec![check_dir(root, value)]
+        } else {
+            return None;
+        };
+
+        Some(Self {
+            key: key.to_owned(),
+            entries,
+        })
+    }
+
+    #[must_use]
+    pub fn has_issues(&self) -> bool {
+        self.entries.iter().any(|entry| !entry.issues.is_empty())
+    }
+
+    /// Find file `name` in entries, in order of precedence.
+    ///
+    /// The first one wins and the others are shadowed. In `PATH` only executable
+    /// files are counted. Relative entries, including empty one, are resolved
+    /// against `cwd`, the working directory of process. Duplicate entries are skipped.
+    #[must_use]
+    pub fn find(&self, root: &SystemRoot, cwd: &Path, name: &str) -> Vec<Found> {
+        if self.key == PRELOAD_VAR {
+            return Vec::new();
+        }
+        self.entries
+            .iter()
+            .filter(|entry| !entry.issues.contains(&Issue::Duplicate))
+            .map(|entry| {
+                let relative = entry.issues.contains(&Issue::Relative);
+                let path = if relative {
+                    cwd.join(&entry.path).join(name)
+                } else {
+                    entry.path.join(name)
+                };
+                Found { path, relative }
+            })
+            .filter(|found| match root.metadata(&found.path) {
+                Ok(metadata) if self.key == "PATH" => {
+                    metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+                }
+                Ok(metadata) => metadata.is_file(),
+                Err(_) => false,
+            })
+            .collect()
+    }
+}
+
+/// File found in search path.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Found {
+    pub path: PathBuf,
+
+    /// Found in a relative entry, so it depends on working directory.
+    pub relative: bool,
+}
+
+fn is_dir_list(key: &str) -> bool {
+    DIR_LIST_VARS.contains(&key) || key.starts_with("XDG_") && key.ends_with("_DIRS")
+}
+
+/// Variables like `XDG_CONFIG_HOME` and `XDG_RUNTIME_DIR` hold only one directory.
+fn is_single_dir(key: &str) -> bool {
+    key.starts_with("XDG_") && (key.ends_with("_HOME") || key.ends_with("_DIR"))
+}
+
+/// Compare paths ignoring trailing slashes, like `/usr/bin/` and `/usr/bin`.
+fn same_path(a: &Path, b: &Path) -> bool {
+    a.components().eq(b.components())
+}
+
+fn check_dir(root: &SystemRoot, item: &str) -> PathEntry {
+    let path = PathBuf::from(item);
+    let mut issues = Vec::new();
+    if path.is_absolute() {
+        match root.metadata(&path) {
+            Ok(metadata) => {
+                if !metadata.is_dir() {
+                    issues.push(Issue::NotDirectory);
+                }
+                if metadata.permissions().mode() & 0o002 != 0 {
+                    issues.push(Issue::WorldWritable);
+                }
+            }
+            Err(_) => issues.push(Issue::Missing),
+        }
+    } else {
+        issues.push(Issue::Relative);
+    }
+    PathEntry { path, issues }
+}
+
+fn check_preload(root: &SystemRoot, item: &str) -> PathEntry {
+    let path = PathBuf::from(item);
+    let mut issues = Vec::new();
+    // Names without slash are searched in library paths, like dependencies.
+    if item.contains('/') {
+        if path.is_absolute() {
+            match root.metadata(&path) {
+                Ok(metadata) if metadata.permissions().mode() & 0o002 != 0 => {
+                    issues.push(Issue::WorldWritable);
+                }
+                Ok(_) => (),
+                Err(_) => issues.push(Issue::Missing),
+            }
+        } else {
+            issues.push(Issue::Relative);
+        }
+    }
+    PathEntry { path, issues }
+}
+
+/// Analyse search path variables in environment `env`, sorted by name.
+///
+/// Entries are checked in `root`, the target system.
+#[must_use]
+pub fn analyze(root: &SystemRoot, env: &[EnvItem]) -> Vec<PathVar> {
+    let mut list: Vec<PathVar> = env
+        .iter()
+        .filter_map(|item| {
+            let key = item.key.to_str()?;
+            PathVar::new(root, key, &item.value.to_string_lossy())
+        })
+        .collect();
+    list.sort_by(|a, b| a.key.cmp(&b.key));
+    list
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsString;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::{Path, PathBuf};
+    use std::{env, process};
+
+    use super::{analyze, Found, Issue, PathVar};
+    use crate::base::system_root::SystemRoot;
+    use crate::computer::environment::EnvItem;
+
+    #[test]
+    fn test_path() {
+        let root = SystemRoot::fixture("laptop");
+        let var = PathVar::new(
+            &root,
+            "PATH",
+            "/usr/local/bin:/usr/bin:/usr/bin/::/opt/bin:bin",
+        )
+        .unwrap();
+        let issues: Vec<&[Issue]> = var.entries.iter().map(|e| e.issues.as_slice()).collect();
+        assert_eq!(
+            issues,
+            [
+                &[][..],
+                &[],
+                &[Issue::Duplicate],
+                &[Issue::Relative],
+                &[Issue::Missing],
+                &[Issue::Relative],
+            ]
+        );
+        assert!(var.has_issues());
+
+        let paths = |found: Vec<Found>| -> Vec<PathBuf> {
+            found.into_iter().map(|found| found.path).collect()
+        };
+        assert_eq!(
+            paths(var.find(&root, Path::new("/"), "python3")),
+            [
+                PathBuf::from("/usr/local/bin/python3"),
+                PathBuf::from("/usr/bin/python3")
+            ]
+        );
+        // Not executable.
+        assert!(var.find(&root, Path::new("/"), "README").is_empty());
+
+        // Current directory wins with an empty entry.
+        let var = PathVar::new(&root, "PATH", ":/usr/bin").unwrap();
+        let found = var.find(&root, Path::new("/usr/local/bin"), "python3");
+        assert_eq!(
+            found[0],
+            Found {
+                path: PathBuf::from("/usr/local/bin/python3"),
+                relative: true,
+            }
+        );
+        assert!(!found[1].relative);
+
+        let var = PathVar::new(&root, "PATH", "/usr/bin:local/bin").unwrap();
+        let found = var.find(&root, Path::new("/usr"), "python3");
+        assert_eq!(
+            paths(found),
+            [
+                PathBuf::from("/usr/bin/python3"),
+                PathBuf::from("/usr/local/bin/python3")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_analyze() {
+        let root = SystemRoot::fixture("laptop");
+        let env: Vec<EnvItem> = [
+            ("XDG_SESSION_TYPE", "wayland"),
+            ("XDG_DATA_DIRS", "/usr/local/share:/usr/share"),
+            ("XDG_CONFIG_HOME", "/etc"),
+            ("MANPATH", ":/usr/bin/python3"),
+            ("LD_PRELOAD", "libfake.so /tmp/inject.so"),
+            ("HOME", "/home/alice"),
+        ]
+        .iter()
+        .map(|(key, value)| EnvItem {
+            key: OsString::from(key),
+            value: OsString::from(value),
+        })
+        .collect();
+
+        let list = analyze(&root, &env);
+        let keys: Vec<&str> = list.iter().map(|var| var.key.as_str()).collect();
+        assert_eq!(
+            keys,
+            ["LD_PRELOAD", "MANPATH", "XDG_CONFIG_HOME", "XDG_DATA_DIRS"]
+        );
+        assert!(list[0].entries[0].issues.is_empty());
+        assert_eq!(list[0].entries[1].issues, [Issue::Missing]);
+        assert_eq!(list[1].entries.len(), 1);
+        assert_eq!(list[1].entries[0].issues, [Issue::NotDirectory]);
+        assert!(!list[2].has_issues());
+        assert_eq!(list[3].entries[0].issues, [Issue::Missing]);
+    }
+
+    #[test]
+    fn test_world_writable() {
+        let dir = env::temp_dir().join(format!("xingtian-search-path-{}", process::id()));
+        fs::create_dir_all(dir.join("bin")).unwrap();
+        fs::set_permissions(dir.join("bin"), fs::Permissions::from_mode(0o777)).unwrap();
+
+        let root = SystemRoot::new(&dir);
+        let var = PathVar::new(&root, "LD_LIBRARY_PATH", "/bin").unwrap();
+        assert_eq!(var.entries[0].issues, [Issue::WorldWritable]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
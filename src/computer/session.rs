@@ -0,0 +1,131 @@
+// Copyright (c) 2023 Xu Shaohua <shaohua@biofan.org>. All rights reserved.
+// Use of this source is governed by General Public License that can be found
+// in the LICENSE file.
+
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use utmp_rs::UtmpEntry;
+
+use crate::base::system_root::SystemRoot;
+use crate::base::utmp;
+use crate::error::Error;
+
+/// Default path of active login records.
+pub const UTMP_FILE: &str = "/run/utmp";
+
+/// Active login session, like output of `who`.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Session {
+    pub user: String,
+    pub tty: String,
+
+    /// Remote host, or display like `:0`, empty for local login.
+    pub host: String,
+    pub login_time: SystemTime,
+
+    /// Time since last input on tty, `None` if tty is not accessible.
+    ///
+    /// It is not reproducible from a snapshot, which does not keep access time
+    /// of tty, so it is always `None` for a replayed root.
+    pub idle: Option<Duration>,
+
+    /// PID of session leader.
+    pub pid: i32,
+}
+
+/// Get active user sessions in `/run/utmp`.
+///
+/// # Errors
+/// Returns error if failed to read or parse utmp file.
+pub fn get_list(root: &SystemRoot) -> Result<Vec<Session>, Error> {
+    get_list_from(root, UTMP_FILE)
+}
+
+/// Get active user sessions in utmp file at `path`.
+///
+/// # Errors
+/// Returns error if failed to read or parse utmp file.
+pub fn get_list_from<P: AsRef<Path>>(root: &SystemRoot, path: P) -> Result<Vec<Session>, Error> {
+    let entries = utmp::read_entries(root, path)?;
+    let now = SystemTime::now();
+
+    let list = entries
+        .into_iter()
+        .filter_map(|entry| {
+            if let UtmpEntry::UserProcess {
+                pid,
+                line,
+                user,
+                host,
+                time,
+                ..
+            } = entry
+            {
+                let idle = get_idle(root, &line, now);
+                Some(Session {
+                    user,
+                    tty: line,
+                    host,
+                    login_time: SystemTime::from(time),
+                    idle,
+                    pid,
+                })
+            } else {
+                None
+            }
+        })
+        .collect();
+    Ok(list)
+}
+
+/// Terminal updates its access time on each input, like `w` does.
+fn get_idle(root: &SystemRoot, tty: &str, now: SystemTime) -> Option<Duration> {
+    if root.is_snapshot() {
+        return None;
+    }
+    let path = Path::new("/dev").join(tty);
+    match root.metadata(&path).and_then(|metadata| {
+        metadata
+            .accessed()
+            .map_err(|err| Error::from_io(&path, err))
+    }) {
+        Ok(atime) => Some(now.duration_since(atime).unwrap_or_default()),
+        Err(err) => {
+            log::warn!("Failed to get idle time of {tty}: {err}");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::get_list;
+    use crate::base::system_root::SystemRoot;
+
+    #[test]
+    fn test_get_list() {
+        let list = get_list(&SystemRoot::fixture("laptop"));
+        assert!(list.is_ok());
+        let list = list.unwrap();
+        assert_eq!(list.len(), 2);
+
+        assert_eq!(list[0].user, "shaohua");
+        assert_eq!(list[0].tty, "tty7");
+        assert_eq!(list[0].host, ":0");
+        assert_eq!(list[0].pid, 3001);
+        assert!(list[0].idle.is_some());
+
+        assert_eq!(list[1].tty, "pts/0");
+        assert_eq!(list[1].host, "192.168.1.20");
+        assert_eq!(
+            list[1].login_time,
+            UNIX_EPOCH + Duration::from_secs(1_697_605_200 + 600)
+        );
+        // Device file not exist.
+        assert!(list[1].idle.is_none());
+    }
+}
@@ -2,42 +2,280 @@
 // Use of this source is governed by General Public License that can be found
 // in the LICENSE file.
 
-use std::fs;
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+use crate::base::system_root::SystemRoot;
 use crate::error::Error;
 
+const UPTIME_FILE: &str = "/proc/uptime";
+const STAT_FILE: &str = "/proc/stat";
+const LOADAVG_FILE: &str = "/proc/loadavg";
+
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LoadAverage {
+    pub one: f64,
+    pub five: f64,
+    pub fifteen: f64,
+
+    /// Number of runnable tasks.
+    pub running: u32,
+
+    /// Number of tasks in system.
+    pub total: u32,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UptimeInfo {
+    pub uptime: Duration,
+
+    /// Sum of idle time of all CPUs.
+    pub idle: Duration,
+
+    /// Number of CPUs in `/proc/stat`.
+    pub cpus: u32,
+
+    /// Time when system booted.
+    pub boot_time: SystemTime,
+    pub load_average: LoadAverage,
+}
+
+impl UptimeInfo {
+    /// Average idle time of each CPU.
+    #[must_use]
+    pub fn idle_per_cpu(&self) -> Duration {
+        self.idle / self.cpus.max(1)
+    }
+
+    /// Ratio of idle time since boot, between 0.0 and 1.0.
+    #[must_use]
+    pub fn idle_ratio(&self) -> f64 {
+        if self.uptime.is_zero() {
+            return 0.0;
+        }
+        self.idle_per_cpu().as_secs_f64() / self.uptime.as_secs_f64()
+    }
+}
+
+/// Values of `CLOCK_BOOTTIME` and `CLOCK_MONOTONIC` of current system.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Clocks {
+    /// Time since boot, including time in suspend.
+    pub boottime: Duration,
+
+    /// Time since boot, excluding time in suspend.
+    pub monotonic: Duration,
+}
+
+impl Clocks {
+    /// Time system spent in suspend since boot.
+    #[must_use]
+    pub const fn suspended(&self) -> Duration {
+        self.boottime.saturating_sub(self.monotonic)
+    }
+}
+
+/// Parse decimal seconds like `12345.67` without losing precision of float.
+fn parse_seconds(s: &str) -> Option<Duration> {
+    let (secs, fraction) = s.split_once('.').unwrap_or((s, ""));
+    if fraction.len() > 9 || !fraction.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let secs = secs.parse().ok()?;
+    // Pad fraction to nanoseconds, like `67` to `670000000`.
+    let nanos = format!("{fraction:0<9}").parse().ok()?;
+    Some(Duration::new(secs, nanos))
+}
+
+/// Read uptime and idle time of all CPUs in `/proc/uptime`.
+fn read_uptime(root: &SystemRoot) -> Result<(Duration, Duration), Error> {
+    let content = root.read_to_string(UPTIME_FILE)?;
+    let mut iter = content.split_ascii_whitespace();
+    let mut next = |field| {
+        let text = iter.next().unwrap_or_default();
+        parse_seconds(text).ok_or_else(|| Error::malformed(UPTIME_FILE, Some(1), field, text))
+    };
+    Ok((next("uptime")?, next("idle")?))
+}
+
 /// # Errors
 /// Returns error if failed to read uptime file or failed to parse file content.
-#[allow(clippy::cast_possible_truncation)]
-#[allow(clippy::cast_sign_loss)]
-pub fn get_uptime() -> Result<Duration, Error> {
-    const FILE: &str = "/proc/uptime";
-    let content = fs::read_to_string(FILE).map_err(|err| Error::IoError(FILE, err))?;
+pub fn get_uptime(root: &SystemRoot) -> Result<Duration, Error> {
+    read_uptime(root).map(|(uptime, _idle)| uptime)
+}
+
+/// Get time when system booted, from `btime` in `/proc/stat`.
+///
+/// # Errors
+/// Returns error if failed to read stat file or `btime` is missing.
+pub fn get_boot_time(root: &SystemRoot) -> Result<SystemTime, Error> {
+    read_stat(root).map(|(boot_time, _cpus)| boot_time)
+}
 
-    let uptime_str = content
-        .split(' ')
-        .next()
-        .ok_or_else(|| Error::ParseFile(FILE, "Failed to split content"))?;
-    let seconds: f64 = uptime_str
-        .parse()
-        .map_err(|_err| Error::ParseFile(FILE, "Failed to convert uptime to integer"))?;
+/// Read boot time and number of CPUs in `/proc/stat`.
+fn read_stat(root: &SystemRoot) -> Result<(SystemTime, u32), Error> {
+    let content = root.read_to_string(STAT_FILE)?;
+    let mut boot_time = None;
+    let mut cpus = 0;
+    for (index, line) in content.lines().enumerate() {
+        if let Some(value) = line.strip_prefix("btime ") {
+            let value = value.trim();
+            let secs = value
+                .parse()
+                .map_err(|_err| Error::malformed(STAT_FILE, Some(index + 1), "btime", value))?;
+            boot_time = Some(UNIX_EPOCH + Duration::from_secs(secs));
+        } else if line.starts_with("cpu") && !line.starts_with("cpu ") {
+            cpus += 1;
+        }
+    }
+    let boot_time = boot_time.ok_or_else(|| Error::malformed(STAT_FILE, None, "btime", ""))?;
+    Ok((boot_time, cpus))
+}
+
+/// Get load averages and task counts in `/proc/loadavg`.
+///
+/// # Errors
+/// Returns error if failed to read loadavg file or failed to parse file content.
+pub fn get_load_average(root: &SystemRoot) -> Result<LoadAverage, Error> {
+    let content = root.read_to_string(LOADAVG_FILE)?;
+    let malformed = |field, text: &str| Error::malformed(LOADAVG_FILE, Some(1), field, text);
 
-    let millis: u64 = (seconds * 1000.0) as u64;
-    let duration = Duration::from_millis(millis);
-    Ok(duration)
+    let parts: Vec<&str> = content.split_ascii_whitespace().collect();
+    if parts.len() < 4 {
+        return Err(malformed("columns", &content));
+    }
+    let load = |field, text: &str| text.parse().map_err(|_err| malformed(field, text));
+    let (running, total) = parts[3]
+        .split_once('/')
+        .ok_or_else(|| malformed("tasks", parts[3]))?;
+
+    Ok(LoadAverage {
+        one: load("one", parts[0])?,
+        five: load("five", parts[1])?,
+        fifteen: load("fifteen", parts[2])?,
+        running: running
+            .parse()
+            .map_err(|_err| malformed("running", running))?,
+        total: total.parse().map_err(|_err| malformed("total", total))?,
+    })
+}
+
+/// Get uptime, idle time, boot time and load averages together.
+///
+/// # Errors
+/// Returns error if failed to read or parse any of the proc files.
+pub fn get_uptime_info(root: &SystemRoot) -> Result<UptimeInfo, Error> {
+    let (uptime, idle) = read_uptime(root)?;
+    let (boot_time, cpus) = read_stat(root)?;
+    Ok(UptimeInfo {
+        uptime,
+        idle,
+        cpus,
+        boot_time,
+        load_average: get_load_average(root)?,
+    })
+}
+
+fn clock_gettime(clock: i32) -> Result<Duration, Error> {
+    let mut time = nc::timespec_t::default();
+    // SAFETY: `time` is a valid timespec which lives until syscall returns.
+    unsafe { nc::clock_gettime(clock, &mut time) }
+        .map_err(|errno| Error::syscall("clock_gettime", errno))?;
+    let secs = u64::try_from(time.tv_sec).unwrap_or_default();
+    let nanos = u32::try_from(time.tv_nsec).unwrap_or_default();
+    Ok(Duration::new(secs, nanos))
+}
+
+/// Get `CLOCK_BOOTTIME` and `CLOCK_MONOTONIC` of current system, so that time
+/// in suspend can be told apart.
+///
+/// # Errors
+/// Returns error if clocks are not supported by kernel.
+pub fn get_clocks() -> Result<Clocks, Error> {
+    // Read monotonic first so that suspended time is never negative.
+    let monotonic = clock_gettime(nc::CLOCK_MONOTONIC)?;
+    let boottime = clock_gettime(nc::CLOCK_BOOTTIME)?;
+    Ok(Clocks {
+        boottime,
+        monotonic,
+    })
 }
 
 #[cfg(test)]
 mod tests {
-    use std::time::Duration;
+    use std::time::{Duration, UNIX_EPOCH};
 
-    use super::get_uptime;
+    use super::{
+        get_clocks, get_load_average, get_uptime, get_uptime_info, parse_seconds, LoadAverage,
+    };
+    use crate::base::system_root::SystemRoot;
+    use crate::error::ErrorKind;
 
     #[test]
     fn test_get_uptime() {
-        let uptime = get_uptime();
+        let uptime = get_uptime(&SystemRoot::default());
         assert!(uptime.is_ok());
         assert!(uptime.unwrap() > Duration::from_secs(1));
     }
+
+    #[test]
+    fn test_get_uptime_fixture() {
+        let uptime = get_uptime(&SystemRoot::fixture("laptop"));
+        assert!(uptime.is_ok());
+        assert_eq!(uptime.unwrap(), Duration::from_millis(12_345_670));
+    }
+
+    #[test]
+    fn test_parse_seconds() {
+        assert_eq!(parse_seconds("0.01"), Some(Duration::from_millis(10)));
+        assert_eq!(parse_seconds("42"), Some(Duration::from_secs(42)));
+        assert_eq!(parse_seconds("1.-1"), None);
+        assert_eq!(parse_seconds(""), None);
+    }
+
+    #[test]
+    fn test_get_uptime_info() {
+        let info = get_uptime_info(&SystemRoot::fixture("laptop"));
+        assert!(info.is_ok());
+        let info = info.unwrap();
+        assert_eq!(info.idle, Duration::from_millis(45_678_900));
+        assert_eq!(info.cpus, 4);
+        assert_eq!(info.idle_per_cpu(), Duration::from_millis(11_419_725));
+        assert!((info.idle_ratio() - 0.925).abs() < 0.001);
+        assert_eq!(
+            info.boot_time,
+            UNIX_EPOCH + Duration::from_secs(1_697_605_200)
+        );
+        assert_eq!(
+            info.load_average,
+            LoadAverage {
+                one: 0.52,
+                five: 0.58,
+                fifteen: 0.59,
+                running: 2,
+                total: 1024,
+            }
+        );
+    }
+
+    #[test]
+    fn test_get_load_average_malformed() {
+        let err = get_load_average(&SystemRoot::fixture("malformed")).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Malformed);
+        assert_eq!(
+            err.to_string(),
+            "Failed to parse `tasks` in `/proc/loadavg:1`, text: `2-1024`"
+        );
+    }
+
+    #[test]
+    fn test_get_clocks() {
+        let clocks = get_clocks();
+        assert!(clocks.is_ok());
+        let clocks = clocks.unwrap();
+        assert!(clocks.boottime >= clocks.monotonic);
+        assert!(clocks.suspended() <= clocks.boottime);
+    }
 }
@@ -0,0 +1,364 @@
+// Copyright (c) 2023 Xu Shaohua <shaohua@biofan.org>. All rights reserved.
+// Use of this source is governed by General Public License that can be found
+// in the LICENSE file.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::base::c_str::from_c_str;
+use crate::base::nss;
+use crate::base::system_root::SystemRoot;
+use crate::computer::group::{self, Group};
+use crate::error::Error;
+
+/// `max_days` of shadow entry which means password never expires.
+const NEVER_EXPIRE_DAYS: u32 = 99999;
+
+/// Shells which deny interactive login.
+const NOLOGIN_SHELLS: &[&str] = &["nologin", "false", "sync", "shutdown", "halt"];
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PasswordState {
+    /// Password hash is set.
+    Set,
+
+    /// Password is empty, login without password is allowed.
+    Empty,
+
+    /// Password is locked with `!` prefix, like `passwd -l`.
+    Locked,
+
+    /// Password login is disabled with `*`, usually system accounts.
+    Disabled,
+}
+
+impl From<&str> for PasswordState {
+    fn from(s: &str) -> Self {
+        if s.is_empty() {
+            Self::Empty
+        } else if s.starts_with('!') {
+            Self::Locked
+        } else if s.starts_with('*') {
+            Self::Disabled
+        } else {
+            Self::Set
+        }
+    }
+}
+
+/// Password metadata in `/etc/shadow`, password hash is not kept.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Shadow {
+    pub password: PasswordState,
+
+    /// Date of last password change, epoch means password shall be changed on next login.
+    pub last_change: Option<SystemTime>,
+    pub min_days: Option<u32>,
+    pub max_days: Option<u32>,
+    pub warn_days: Option<u32>,
+    pub inactive_days: Option<u32>,
+
+    /// Date when account expires.
+    pub expire: Option<SystemTime>,
+}
+
+impl Shadow {
+    /// Returns true if password has a maximum age.
+    #[must_use]
+    pub const fn password_expires(&self) -> bool {
+        matches!(self.max_days, Some(days) if days < NEVER_EXPIRE_DAYS)
+    }
+
+    /// Date when password expires.
+    #[must_use]
+    pub fn password_expire(&self) -> Option<SystemTime> {
+        if !self.password_expires() {
+            return None;
+        }
+        let days = self.max_days?;
+        Some(self.last_change? + days_to_duration(u64::from(days)))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct User {
+    pub name: String,
+    pub uid: u32,
+    pub gid: u32,
+    pub gecos: String,
+    pub home: String,
+    pub shell: String,
+
+    /// Password metadata, `None` if `/etc/shadow` is not readable.
+    pub shadow: Option<Shadow>,
+
+    /// Name of primary group and supplementary groups.
+    pub groups: Vec<String>,
+}
+
+impl User {
+    /// Returns true if shell of user allows interactive login.
+    #[must_use]
+    pub fn has_login_shell(&self) -> bool {
+        let name = self.shell.rsplit('/').next().unwrap_or_default();
+        !name.is_empty() && !NOLOGIN_SHELLS.contains(&name)
+    }
+
+    /// Returns true if password of user can be used to login and never expires.
+    ///
+    /// Returns `None` if password metadata is unknown.
+    #[must_use]
+    pub fn password_never_expires(&self) -> Option<bool> {
+        let shadow = self.shadow.as_ref()?;
+        Some(
+            matches!(shadow.password, PasswordState::Set | PasswordState::Empty)
+                && !shadow.password_expires(),
+        )
+    }
+}
+
+const fn days_to_duration(days: u64) -> Duration {
+    Duration::from_secs(days * 86400)
+}
+
+/// Parse accounts in `/etc/passwd`, with metadata in `/etc/shadow` if it is
+/// readable and groups in `/etc/group`.
+///
+/// # Errors
+/// Returns error if failed to read or parse passwd file.
+pub fn get_user_list(root: &SystemRoot) -> Result<Vec<User>, Error> {
+    const FILE: &str = "/etc/passwd";
+    let content = root.read_to_string(FILE)?;
+
+    let mut list = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('+') || line.starts_with('-') {
+            log::warn!("Ignore NIS entry in {FILE}: {line}");
+            continue;
+        }
+
+        let line_num = Some(index + 1);
+        let parts: Vec<&str> = line.split(':').collect();
+        if parts.len() != 7 {
+            return Err(Error::malformed(FILE, line_num, "columns", line));
+        }
+        let uid = parts[2]
+            .parse()
+            .map_err(|_err| Error::malformed(FILE, line_num, "uid", parts[2]))?;
+        let gid = parts[3]
+            .parse()
+            .map_err(|_err| Error::malformed(FILE, line_num, "gid", parts[3]))?;
+        list.push(User {
+            name: parts[0].to_owned(),
+            uid,
+            gid,
+            gecos: parts[4].to_owned(),
+            home: parts[5].to_owned(),
+            shell: parts[6].to_owned(),
+            shadow: None,
+            groups: Vec::new(),
+        });
+    }
+
+    // shadow is only readable by root.
+    if let Err(err) = merge_shadow(root, &mut list) {
+        log::info!("Skip shadow: {err}");
+    }
+
+    match group::get_group_list(root) {
+        Ok(groups) => merge_groups(&mut list, &groups),
+        Err(err) => log::warn!("Failed to get groups of users: {err}"),
+    }
+
+    Ok(list)
+}
+
+fn merge_shadow(root: &SystemRoot, list: &mut [User]) -> Result<(), Error> {
+    const FILE: &str = "/etc/shadow";
+    let content = root.read_to_string(FILE)?;
+
+    for (index, line) in content.lines().enumerate() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line_num = Some(index + 1);
+        let parts: Vec<&str> = line.split(':').collect();
+        if parts.len() != 9 {
+            return Err(Error::malformed(FILE, line_num, "columns", line));
+        }
+
+        let number = |field: &'static str, s: &str| -> Result<Option<u32>, Error> {
+            if s.is_empty() {
+                Ok(None)
+            } else {
+                s.parse()
+                    .map(Some)
+                    .map_err(|_err| Error::malformed(FILE, line_num, field, s))
+            }
+        };
+        let date = |field: &'static str, s: &str| -> Result<Option<SystemTime>, Error> {
+            Ok(number(field, s)?.map(|days| UNIX_EPOCH + days_to_duration(u64::from(days))))
+        };
+
+        let shadow = Shadow {
+            password: parts[1].into(),
+            last_change: date("last_change", parts[2])?,
+            min_days: number("min_days", parts[3])?,
+            max_days: number("max_days", parts[4])?,
+            warn_days: number("warn_days", parts[5])?,
+            inactive_days: number("inactive_days", parts[6])?,
+            expire: date("expire", parts[7])?,
+        };
+        if let Some(user) = list.iter_mut().find(|user| user.name == parts[0]) {
+            user.shadow = Some(shadow);
+        }
+    }
+
+    Ok(())
+}
+
+fn merge_groups(list: &mut [User], groups: &[Group]) {
+    for user in list {
+        if let Some(primary) = group::find_by_gid(groups, user.gid) {
+            user.groups.push(primary.name.clone());
+        }
+        for group in group::find_by_member(groups, &user.name) {
+            if !user.groups.contains(&group.name) {
+                user.groups.push(group.name.clone());
+            }
+        }
+    }
+}
+
+/// Enumerate accounts of current system through NSS, so that users in LDAP
+/// or SSSD are listed too.
+///
+/// Groups are also enumerated through NSS.
+#[must_use]
+pub fn get_user_list_nss() -> Vec<User> {
+    let mut list = Vec::new();
+
+    let guard = nss::lock();
+    // SAFETY: Entries returned by getpwent() are valid until next call,
+    // they are copied before that. Enumeration is serialized by NSS lock.
+    unsafe {
+        libc::setpwent();
+        loop {
+            let entry = libc::getpwent();
+            if entry.is_null() {
+                break;
+            }
+            let entry = &*entry;
+            list.push(User {
+                name: from_c_str(entry.pw_name),
+                uid: entry.pw_uid,
+                gid: entry.pw_gid,
+                gecos: from_c_str(entry.pw_gecos),
+                home: from_c_str(entry.pw_dir),
+                shell: from_c_str(entry.pw_shell),
+                shadow: None,
+                groups: Vec::new(),
+            });
+        }
+        libc::endpwent();
+    }
+    // Groups take the same lock.
+    drop(guard);
+
+    merge_groups(&mut list, &group::get_group_list_nss());
+    list
+}
+
+/// Find user with `uid`.
+#[must_use]
+pub fn find_by_uid(list: &[User], uid: u32) -> Option<&User> {
+    list.iter().find(|user| user.uid == uid)
+}
+
+/// Find user with `name`.
+#[must_use]
+pub fn find_by_name<'a>(list: &'a [User], name: &str) -> Option<&'a User> {
+    list.iter().find(|user| user.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::{find_by_name, find_by_uid, get_user_list, get_user_list_nss, PasswordState};
+    use crate::base::system_root::SystemRoot;
+    use crate::error::ErrorKind;
+
+    #[test]
+    fn test_get_user_list() {
+        let list = get_user_list(&SystemRoot::fixture("laptop"));
+        assert!(list.is_ok());
+        let list = list.unwrap();
+        assert_eq!(list.len(), 8);
+
+        let shaohua = find_by_uid(&list, 1000).unwrap();
+        assert_eq!(shaohua.name, "shaohua");
+        assert_eq!(shaohua.gecos, "Xu Shaohua,,,");
+        assert_eq!(shaohua.home, "/home/shaohua");
+        assert!(shaohua.has_login_shell());
+        assert_eq!(
+            shaohua.groups,
+            ["shaohua", "adm", "sudo", "plugdev", "docker"]
+        );
+        let shadow = shaohua.shadow.as_ref().unwrap();
+        assert_eq!(shadow.password, PasswordState::Set);
+        assert_eq!(shadow.max_days, Some(90));
+        assert_eq!(
+            shadow.password_expire(),
+            Some(UNIX_EPOCH + Duration::from_secs((19600 + 90) * 86400))
+        );
+        assert_eq!(shaohua.password_never_expires(), Some(false));
+
+        let root = find_by_name(&list, "root").unwrap();
+        assert_eq!(
+            root.shadow.as_ref().map(|s| s.password),
+            Some(PasswordState::Locked)
+        );
+        assert_eq!(root.password_never_expires(), Some(false));
+
+        let daemon = find_by_name(&list, "daemon").unwrap();
+        assert!(!daemon.has_login_shell());
+
+        let deploy = find_by_name(&list, "deploy").unwrap();
+        assert!(!deploy.has_login_shell());
+        assert_eq!(deploy.groups, ["docker"]);
+        let shadow = deploy.shadow.as_ref().unwrap();
+        assert_eq!(shadow.password, PasswordState::Empty);
+        assert_eq!(
+            shadow.expire,
+            Some(UNIX_EPOCH + Duration::from_secs(19700 * 86400))
+        );
+
+        // Accounts with a login shell and password never expires.
+        let names: Vec<&str> = list
+            .iter()
+            .filter(|user| user.has_login_shell() && user.password_never_expires() == Some(true))
+            .map(|user| user.name.as_str())
+            .collect();
+        assert_eq!(names, ["ci"]);
+    }
+
+    #[test]
+    fn test_get_user_list_malformed() {
+        let err = get_user_list(&SystemRoot::fixture("malformed")).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Malformed);
+    }
+
+    #[test]
+    fn test_get_user_list_nss() {
+        let list = get_user_list_nss();
+        let root = find_by_uid(&list, 0).unwrap();
+        assert_eq!(root.name, "root");
+        assert!(root.groups.contains(&"root".to_owned()));
+    }
+}
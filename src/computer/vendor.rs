@@ -2,6 +2,11 @@
 // Use of this source is governed by General Public License that can be found
 // in the LICENSE file.
 
+use std::cmp::Ordering;
+
+use aho_corasick::AhoCorasick;
+use once_cell::sync::Lazy;
+
 #[derive(Debug, Clone)]
 pub struct Vendor {
     pub id: &'static str,
@@ -108,51 +113,173 @@ const VENDOR_LIST: &[Vendor] = &[
     Vendor::new("XenVMMXenVMM", "Xen HVM", ""),
 ];
 
-#[must_use]
-pub fn get_name(id: &str) -> Option<&'static str> {
-    for vendor in VENDOR_LIST {
-        if id.contains(vendor.id) {
-            return Some(vendor.name);
-        }
+/// Vendor found in a device or firmware string.
+#[derive(Debug, Clone)]
+pub struct VendorMatch {
+    pub vendor: &'static Vendor,
+
+    /// Byte range of vendor id in the string.
+    pub start: usize,
+    pub end: usize,
+
+    /// How likely the match is right, between 0.0 and 1.0.
+    pub confidence: f32,
+}
+
+/// Index of vendor ids, matched case-insensitively.
+static VENDOR_INDEX: Lazy<AhoCorasick> = Lazy::new(|| {
+    AhoCorasick::builder()
+        .ascii_case_insensitive(true)
+        .build(VENDOR_LIST.iter().map(|vendor| vendor.id))
+        .expect("Invalid vendor id")
+});
+
+/// Ids like `ST` and `hp` are common abbreviations, trust them less.
+const SHORT_ID_LEN: usize = 2;
+
+/// Ids which also prefix model numbers, like `ST1000LM035` of SEAGATE.
+const MODEL_PREFIXES: &[&str] = &["ST"];
+
+/// Model prefix is only a guess, trust it less than any word match.
+const MODEL_PREFIX_CONFIDENCE: f32 = 0.3;
+
+/// Check that `start..end` of `text` is not part of a longer word.
+///
+/// Ids which begin or end with punctuation, like `_NEC`, need no boundary at that side.
+fn at_word_boundary(text: &[u8], start: usize, end: usize) -> bool {
+    let is_word = |b: &u8| b.is_ascii_alphanumeric();
+    let before = start == 0 || !is_word(&text[start]) || !is_word(&text[start - 1]);
+    let after = end == text.len() || !is_word(&text[end - 1]) || !is_word(&text[end]);
+    before && after
+}
+
+/// Check that `start..end` of `text` is a model prefix, followed by digits.
+fn is_model_prefix(text: &str, vendor: &Vendor, start: usize, end: usize) -> bool {
+    let bytes = text.as_bytes();
+    MODEL_PREFIXES.contains(&vendor.id)
+        && &text[start..end] == vendor.id
+        && (start == 0 || !bytes[start - 1].is_ascii_alphanumeric())
+        && bytes.get(end).map_or(false, u8::is_ascii_digit)
+}
+
+fn confidence(text: &str, vendor: &Vendor, start: usize, end: usize) -> f32 {
+    let mut confidence: f32 = if text.trim().eq_ignore_ascii_case(vendor.id) {
+        1.0
+    } else if &text[start..end] == vendor.id {
+        0.9
+    } else {
+        0.6
+    };
+    if vendor.id.len() <= SHORT_ID_LEN {
+        confidence -= 0.2;
     }
-    None
+    confidence
 }
 
+/// Find vendor of device or firmware string `text`, like
+/// `Realtek Semiconductor Co., Ltd. RTL8821CE`.
+///
+/// Vendor ids are matched case-insensitively at word boundaries. If more than
+/// one id is found, the one with highest confidence wins, then the longest one,
+/// then the first one in `text`. Model numbers like `ST1000LM035` are matched
+/// by their prefix too, with a lower confidence.
 #[must_use]
-pub fn get_url(id: &str) -> Option<&'static str> {
-    for vendor in VENDOR_LIST {
-        if id.contains(vendor.id) {
-            return Some(vendor.url);
-        }
-    }
-    None
+pub fn find(text: &str) -> Option<VendorMatch> {
+    let bytes = text.as_bytes();
+    VENDOR_INDEX
+        .find_overlapping_iter(text)
+        .filter_map(|m| {
+            let vendor = &VENDOR_LIST[m.pattern().as_usize()];
+            let confidence = if at_word_boundary(bytes, m.start(), m.end()) {
+                confidence(text, vendor, m.start(), m.end())
+            } else if is_model_prefix(text, vendor, m.start(), m.end()) {
+                MODEL_PREFIX_CONFIDENCE
+            } else {
+                return None;
+            };
+            Some(VendorMatch {
+                vendor,
+                start: m.start(),
+                end: m.end(),
+                confidence,
+            })
+        })
+        .reduce(|best, m| {
+            let order = m
+                .confidence
+                .total_cmp(&best.confidence)
+                .then((m.end - m.start).cmp(&(best.end - best.start)))
+                .then(best.start.cmp(&m.start));
+            // Keep the earlier one in vendor table if both are equal.
+            if order == Ordering::Greater {
+                m
+            } else {
+                best
+            }
+        })
+}
+
+/// Find vendor of `text`, see [`find`].
+#[must_use]
+pub fn get_vendor(text: &str) -> Option<&'static Vendor> {
+    find(text).map(|m| m.vendor)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{get_name, get_url};
+    use super::{find, get_vendor};
 
     #[test]
-    fn test_get_name() {
-        assert_eq!(
-            get_name("Realtek Semiconductor Co., Ltd. RTL8821CE"),
-            Some("Realtek")
-        );
+    fn test_get_vendor() {
+        let vendor = get_vendor("Realtek Semiconductor Co., Ltd. RTL8821CE").unwrap();
+        assert_eq!(vendor.name, "Realtek");
+        assert_eq!(vendor.url, "https://www.realtek.com");
+
+        let vendor = get_vendor("Hewlett-Packard Company RTL8821CE").unwrap();
+        assert_eq!(vendor.name, "Hewlett-Packard");
+        assert_eq!(vendor.url, "https://www.hp.com");
+
+        assert_eq!(get_vendor("GenuineIntel").map(|v| v.name), Some("Intel"));
         assert_eq!(
-            get_name("Hewlett-Packard Company RTL8821CE"),
-            Some("Hewlett-Packard")
+            get_vendor("intel corporation").map(|v| v.name),
+            Some("Intel")
         );
+        assert!(get_vendor("Unknown Device").is_none());
     }
 
     #[test]
-    fn test_get_url() {
+    fn test_word_boundary() {
+        // `ST` and `hp` inside other words are not vendors.
+        assert!(get_vendor("Best Chip").is_none());
+        assert!(get_vendor("Graphics").is_none());
         assert_eq!(
-            get_url("Realtek Semiconductor Co., Ltd. RTL8821CE"),
-            Some("https://www.realtek.com")
-        );
-        assert_eq!(
-            get_url("Hewlett-Packard Company RTL8821CE"),
-            Some("https://www.hp.com")
+            get_vendor("TOSHIBA MQ04ABF100 ST").map(|v| v.name),
+            Some("TOSHIBA")
         );
+        assert_eq!(get_vendor("ST1000LM035").map(|v| v.name), Some("SEAGATE"));
+        // Only upper case prefix followed by digits is a model number.
+        assert!(get_vendor("st1000").is_none());
+        assert!(get_vendor("STRIX").is_none());
+        assert_eq!(get_vendor("ST 1000LM035").map(|v| v.name), Some("SEAGATE"));
+    }
+
+    #[test]
+    fn test_find() {
+        let m = find("VIA Technologies, Inc. VT6105").unwrap();
+        assert_eq!(m.vendor.id, "VIA Technologies");
+        assert_eq!((m.start, m.end), (0, 16));
+        assert!((m.confidence - 0.9).abs() < f32::EPSILON);
+
+        let m = find("Dell").unwrap();
+        assert!((m.confidence - 1.0).abs() < f32::EPSILON);
+
+        let m = find("ST1000LM035-1RK172").unwrap();
+        assert_eq!(m.vendor.name, "SEAGATE");
+        assert_eq!((m.start, m.end), (0, 2));
+        assert!(m.confidence < 0.4);
+
+        let m = find("hp LaserJet").unwrap();
+        assert_eq!(m.vendor.name, "Hewlett-Packard");
+        assert!(m.confidence < 0.9);
     }
 }
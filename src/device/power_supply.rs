@@ -2,18 +2,22 @@
 // Use of this source is governed by General Public License that can be found
 // in the LICENSE file.
 
-use std::fs;
 use std::path::Path;
+use std::str::FromStr;
 
+use crate::base::parse_mode::{ParseMode, Parsed};
+use crate::base::system_root::SystemRoot;
 use crate::error::Error;
 
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PowerSupply {
     pub name: String,
     pub manufacturer: String,
     pub model_name: String,
     pub serial_number: String,
     pub technology: Technology,
+    #[cfg_attr(feature = "serde", serde(rename = "type"))]
     pub type_: Type,
     pub status: Status,
     pub present: bool,
@@ -39,6 +43,7 @@ pub struct PowerSupply {
 ///
 /// From `power_supply_sysfs.c`
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(non_camel_case_types)]
 pub enum Type {
     Unknown,
@@ -90,6 +95,7 @@ impl From<&str> for Type {
 ///
 /// From `power_supply_sysfs.c`
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Status {
     Unknown,
     Charging,
@@ -124,6 +130,7 @@ impl From<&str> for Status {
 ///
 /// From `power_supply_sysfs.c`
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Technology {
     Unknown,
     NiMH,
@@ -162,6 +169,7 @@ impl From<&str> for Technology {
 ///
 /// From `power_supply_sysfs.c`
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CapacityLevel {
     Unknown,
     Critical,
@@ -196,145 +204,238 @@ impl From<&str> for CapacityLevel {
 
 /// # Errors
 /// Returns error if failed to read power supply file.
-pub fn get_list() -> Result<Vec<PowerSupply>, Error> {
+pub fn get_list(root: &SystemRoot) -> Result<Vec<PowerSupply>, Error> {
+    get_list_with_mode(root, ParseMode::Strict).map(|parsed| parsed.value)
+}
+
+/// Same as [`get_list`], but in lenient mode attributes which failed to parse
+/// are left as default and batteries which failed to read are skipped.
+///
+/// # Errors
+/// Returns error if failed to read power supply directory, or failed to parse
+/// any battery in strict mode.
+pub fn get_list_with_mode(
+    root: &SystemRoot,
+    mode: ParseMode,
+) -> Result<Parsed<Vec<PowerSupply>>, Error> {
     const DIR: &str = "/sys/class/power_supply";
-    let mut list = Vec::new();
-
-    for entry in fs::read_dir(DIR).map_err(|err| Error::IoError(DIR, err))? {
-        let entry = entry.map_err(|err| Error::IoError(DIR, err))?;
-        let path = entry.path();
-        if path.is_dir() {
-            let filename = path.file_name().unwrap_or_default();
-            if filename.is_ascii() && filename.to_str().unwrap_or_default().starts_with("BAT") {
-                let battery = read_detail(&path)?;
-                list.push(battery);
+    let mut parsed = Parsed::<Vec<PowerSupply>>::default();
+
+    for filename in root.read_dir(DIR)? {
+        let path = Path::new(DIR).join(&filename);
+        if root.is_dir(&path) && filename.starts_with("BAT") {
+            let result = read_detail_with_mode(root, &path, mode);
+            if let Some(mut battery) = mode.check(result, &mut parsed.warnings)? {
+                parsed.warnings.append(&mut battery.warnings);
+                parsed.value.push(battery.value);
             }
         }
     }
 
-    Ok(list)
+    Ok(parsed)
 }
 
+/// Parse power supply directory at `dir`, like `/sys/class/power_supply/BAT0`.
+///
 /// # Errors
 /// Returns error if failed to parse power supply directory.
-pub fn read_detail(dir: &Path) -> Result<PowerSupply, Error> {
-    const FILE: &str = "power_supply/uevent";
+pub fn read_detail(root: &SystemRoot, dir: &Path) -> Result<PowerSupply, Error> {
+    read_detail_with_mode(root, dir, ParseMode::Strict).map(|parsed| parsed.value)
+}
 
-    let uevent = fs::read_to_string(dir.join("uevent")).map_err(|err| Error::IoError(FILE, err))?;
+/// Same as [`read_detail`], but attributes which failed to parse are left
+/// as default in lenient mode.
+///
+/// # Errors
+/// Returns error if failed to read uevent file, or failed to parse any
+/// attribute in strict mode.
+pub fn read_detail_with_mode(
+    root: &SystemRoot,
+    dir: &Path,
+    mode: ParseMode,
+) -> Result<Parsed<PowerSupply>, Error> {
+    let uevent_path = dir.join("uevent");
+    let uevent = root.read_to_string(&uevent_path)?;
+
+    let mut parsed = Parsed::<PowerSupply>::default();
+    let ps = &mut parsed.value;
+
+    for (index, line) in uevent.lines().enumerate() {
+        let result = parse_uevent_line(ps, &uevent_path, Some(index + 1), line);
+        mode.check(result, &mut parsed.warnings)?;
+    }
 
-    let mut ps = PowerSupply::default();
+    let result = read_number(root, dir, "alarm");
+    if let Some(Some(alarm)) = mode.check(result, &mut parsed.warnings)? {
+        ps.alarm = alarm;
+    }
+    let result = read_number(root, dir, "charge_control_end_threshold");
+    if let Some(Some(threshold)) = mode.check(result, &mut parsed.warnings)? {
+        ps.charge_control_end_threshold = threshold;
+    }
+    let result = read_number(root, dir, "charge_control_start_threshold");
+    if let Some(Some(threshold)) = mode.check(result, &mut parsed.warnings)? {
+        ps.charge_control_start_threshold = threshold;
+    }
 
-    for line in uevent.lines() {
-        if !line.starts_with("POWER_SUPPLY_") {
-            log::warn!("Invalid power supply attr: {line}");
-            continue;
-        }
+    Ok(parsed)
+}
 
-        let mut parts = line["POWER_SUPPLY_".len()..].split('=');
-        let key = if let Some(key) = parts.next() {
-            key
-        } else {
-            log::warn!("Invalid power supply attr key: {line}");
-            continue;
-        };
-        let value = if let Some(value) = parts.next() {
-            value.trim()
-        } else {
-            log::warn!("Invalid power supply attr value: {line}");
-            continue;
-        };
-
-        match key {
-            "NAME" => ps.name = value.to_owned(),
-            "TYPE" => ps.type_ = value.into(),
-            "STATUS" => ps.status = value.into(),
-            "PRESENT" => ps.present = value == "1",
-            "TECHNOLOGY" => ps.technology = value.into(),
-            "CYCLE_COUNT" => {
-                ps.cycle_count = value
-                    .parse()
-                    .map_err(|_err| Error::ParseFile(FILE, "Failed to parse cycle count"))?;
-            }
-            "VOLTAGE_MIN_DESIGN" => {
-                ps.voltage_min_design = value
-                    .parse()
-                    .map_err(|_err| Error::ParseFile(FILE, "Failed to parse voltage_min_design"))?;
-            }
-            "VOLTAGE_NOW" => {
-                ps.voltage_now = value
-                    .parse()
-                    .map_err(|_err| Error::ParseFile(FILE, "Failed to parse voltage_now"))?;
-            }
-            "CURRENT_NOW" => {
-                ps.current_now = value
-                    .parse()
-                    .map_err(|_err| Error::ParseFile(FILE, "Failed to parse current_now"))?;
-            }
-            "CHARGE_FULL_DESIGN" => {
-                ps.charge_full_design = value
-                    .parse()
-                    .map_err(|_err| Error::ParseFile(FILE, "Failed to parse charge_full_design"))?;
-            }
-            "CHARGE_FULL" => {
-                ps.charge_full = value
-                    .parse()
-                    .map_err(|_err| Error::ParseFile(FILE, "Failed to parse charge_full"))?;
-            }
-            "CHARGE_NOW" => {
-                ps.charge_now = value
-                    .parse()
-                    .map_err(|_err| Error::ParseFile(FILE, "Failed to parse charge_now"))?;
-            }
-            "CAPACITY" => {
-                ps.capacity = value
-                    .parse()
-                    .map_err(|_err| Error::ParseFile(FILE, "Failed to parse capacity"))?;
-            }
-            "CAPACITY_LEVEL" => ps.capacity_level = value.into(),
-            "MODEL_NAME" => ps.model_name = value.to_owned(),
-            "MANUFACTURER" => ps.manufacturer = value.to_owned(),
-            "SERIAL_NUMBER" => ps.serial_number = value.to_owned(),
-            _key => log::warn!("Invalid power supply attr key: {line}"),
-        }
+/// Parse one `POWER_SUPPLY_XXX=value` line of uevent file.
+fn parse_uevent_line(
+    ps: &mut PowerSupply,
+    path: &Path,
+    line_num: Option<usize>,
+    line: &str,
+) -> Result<(), Error> {
+    if !line.starts_with("POWER_SUPPLY_") {
+        log::warn!("Invalid power supply attr: {line}");
+        return Ok(());
     }
 
-    if let Ok(alarm_str) = fs::read_to_string(dir.join("alarm")) {
-        ps.alarm = alarm_str
-            .trim()
-            .parse()
-            .map_err(|_err| Error::ParseFile("power_supply/alarm", ""))?;
+    let mut parts = line["POWER_SUPPLY_".len()..].split('=');
+    let key = if let Some(key) = parts.next() {
+        key
     } else {
-        log::warn!("Failed to read power_supply/alarm file");
-    }
-
-    if let Ok(s) = fs::read_to_string(dir.join("charge_control_end_threshold")) {
-        ps.charge_control_end_threshold = s
-            .trim()
-            .parse()
-            .map_err(|_err| Error::ParseFile("power_supply/charge_control_end_threshold", ""))?;
+        log::warn!("Invalid power supply attr key: {line}");
+        return Ok(());
+    };
+    let value = if let Some(value) = parts.next() {
+        value.trim()
     } else {
-        log::warn!("Failed to read power_supply/charge_control_end_threshold file");
+        log::warn!("Invalid power supply attr value: {line}");
+        return Ok(());
+    };
+
+    let to_error = |field| Error::malformed(path, line_num, field, value);
+    match key {
+        "NAME" => ps.name = value.to_owned(),
+        "TYPE" => ps.type_ = value.into(),
+        "STATUS" => ps.status = value.into(),
+        "PRESENT" => ps.present = value == "1",
+        "TECHNOLOGY" => ps.technology = value.into(),
+        "CYCLE_COUNT" => {
+            ps.cycle_count = value.parse().map_err(|_err| to_error("cycle_count"))?;
+        }
+        "VOLTAGE_MIN_DESIGN" => {
+            ps.voltage_min_design = value
+                .parse()
+                .map_err(|_err| to_error("voltage_min_design"))?;
+        }
+        "VOLTAGE_NOW" => {
+            ps.voltage_now = value.parse().map_err(|_err| to_error("voltage_now"))?;
+        }
+        "CURRENT_NOW" => {
+            ps.current_now = value.parse().map_err(|_err| to_error("current_now"))?;
+        }
+        "CHARGE_FULL_DESIGN" => {
+            ps.charge_full_design = value
+                .parse()
+                .map_err(|_err| to_error("charge_full_design"))?;
+        }
+        "CHARGE_FULL" => {
+            ps.charge_full = value.parse().map_err(|_err| to_error("charge_full"))?;
+        }
+        "CHARGE_NOW" => {
+            ps.charge_now = value.parse().map_err(|_err| to_error("charge_now"))?;
+        }
+        "CAPACITY" => {
+            ps.capacity = value.parse().map_err(|_err| to_error("capacity"))?;
+        }
+        "CAPACITY_LEVEL" => ps.capacity_level = value.into(),
+        "MODEL_NAME" => ps.model_name = value.to_owned(),
+        "MANUFACTURER" => ps.manufacturer = value.to_owned(),
+        "SERIAL_NUMBER" => ps.serial_number = value.to_owned(),
+        _key => log::warn!("Invalid power supply attr key: {line}"),
     }
+    Ok(())
+}
 
-    if let Ok(s) = fs::read_to_string(dir.join("charge_control_start_threshold")) {
-        ps.charge_control_start_threshold = s
-            .trim()
-            .parse()
-            .map_err(|_err| Error::ParseFile("power_supply/charge_control_start_threshold", ""))?;
+/// Read number in attribute file, returns `None` if file is not readable.
+fn read_number<T: FromStr>(
+    root: &SystemRoot,
+    dir: &Path,
+    file: &'static str,
+) -> Result<Option<T>, Error> {
+    let path = dir.join(file);
+    let s = if let Ok(s) = root.read_to_string(&path) {
+        s
     } else {
-        log::warn!("Failed to read power_supply/charge_control_start_threshold file");
-    }
-
-    Ok(ps)
+        log::warn!("Failed to read power_supply/{file} file");
+        return Ok(None);
+    };
+    let s = s.trim();
+    s.parse()
+        .map(Some)
+        .map_err(|_err| Error::malformed(&path, Some(1), file, s))
 }
 
 #[cfg(test)]
 mod tests {
-    use super::get_list;
+    use super::{get_list, get_list_with_mode, CapacityLevel, Status, Technology, Type};
+    use crate::base::parse_mode::ParseMode;
+    use crate::base::system_root::SystemRoot;
 
     #[test]
     fn test_get_list() {
-        let list = get_list();
+        let list = get_list(&SystemRoot::default());
+        assert!(list.is_ok());
+    }
+
+    #[test]
+    fn test_get_list_fixture() {
+        let list = get_list(&SystemRoot::fixture("laptop"));
         assert!(list.is_ok());
+        let list = list.unwrap();
+        assert_eq!(list.len(), 1);
+        let battery = &list[0];
+        assert_eq!(battery.name, "BAT0");
+        assert_eq!(battery.type_, Type::Battery);
+        assert_eq!(battery.status, Status::Discharging);
+        assert_eq!(battery.technology, Technology::LiIon);
+        assert_eq!(battery.capacity_level, CapacityLevel::Normal);
+        assert!(battery.present);
+        assert_eq!(battery.cycle_count, 112);
+        assert_eq!(battery.charge_full, 3_712_000);
+        assert_eq!(battery.capacity, 79);
+        assert_eq!(battery.charge_control_end_threshold, 80);
+        assert_eq!(battery.charge_control_start_threshold, 75);
+    }
+
+    #[test]
+    fn test_get_list_lenient() {
+        let root = SystemRoot::fixture("malformed");
+        assert!(get_list(&root).is_err());
+
+        let parsed = get_list_with_mode(&root, ParseMode::Lenient).unwrap();
+        assert_eq!(parsed.value.len(), 1);
+        let battery = &parsed.value[0];
+        assert_eq!(battery.name, "BAT0");
+        assert_eq!(battery.cycle_count, 0);
+        assert_eq!(battery.capacity, 79);
+        assert_eq!(battery.charge_control_end_threshold, 80);
+        let warnings: Vec<String> = parsed.warnings.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            warnings,
+            [
+                "Failed to parse `cycle_count` in `/sys/class/power_supply/BAT0/uevent:6`, text: ``",
+                "Failed to parse `alarm` in `/sys/class/power_supply/BAT0/alarm:1`, text: ``",
+            ]
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let list = get_list(&SystemRoot::fixture("laptop")).unwrap();
+        let json = serde_json::to_value(&list[0]).unwrap();
+        assert_eq!(json["name"], "BAT0");
+        assert_eq!(json["type"], "Battery");
+        assert_eq!(json["status"], "Discharging");
+        assert_eq!(json["capacity_level"], "Normal");
+
+        let battery: super::PowerSupply = serde_json::from_value(json).unwrap();
+        assert_eq!(battery.type_, Type::Battery);
+        assert_eq!(battery.charge_full, 3_712_000);
     }
 }
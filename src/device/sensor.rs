@@ -5,6 +5,7 @@
 use std::path::PathBuf;
 
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Sensor {
     pub name: String,
     pub driver_path: PathBuf,
@@ -2,12 +2,13 @@
 // Use of this source is governed by General Public License that can be found
 // in the LICENSE file.
 
-use std::fs;
 use std::path::Path;
 
+use crate::base::system_root::SystemRoot;
 use crate::error::Error;
 
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct UsbDev {
     // These attributes are read from sysfs.
     pub max_power: String,
@@ -43,57 +44,55 @@ pub struct UsbDev {
 
 /// # Errors
 /// Returns error if failed to parse usb devices.
-pub fn scan_usb() -> Result<Vec<UsbDev>, Error> {
+pub fn scan_usb(root: &SystemRoot) -> Result<Vec<UsbDev>, Error> {
     const DIR: &str = "/sys/bus/usb/devices";
     let mut list = Vec::new();
 
-    for entry in fs::read_dir(DIR).map_err(|err| Error::IoError(DIR, err))? {
-        let entry = entry.map_err(|err| Error::IoError(DIR, err))?;
-        let path = entry.path();
-        if let Some(s) = path.to_str() {
-            if !s.contains(':') {
-                let dev = scan_usb_event(&path)?;
-                list.push(dev);
-            }
+    for filename in root.read_dir(DIR)? {
+        // Skip usb interfaces, like `1-1:1.0`.
+        if !filename.contains(':') {
+            let dev = scan_usb_event(root, &Path::new(DIR).join(filename))?;
+            list.push(dev);
         }
     }
 
     Ok(list)
 }
 
+/// Parse usb device directory at `dir`, like `/sys/bus/usb/devices/1-1`.
+///
 /// # Errors
 /// Returns error if failed to parse usb udev info.
-pub fn scan_usb_event(dir: &Path) -> Result<UsbDev, Error> {
+pub fn scan_usb_event(root: &SystemRoot, dir: &Path) -> Result<UsbDev, Error> {
     let mut dev = UsbDev::default();
 
-    if let Ok(s) = fs::read_to_string(dir.join("bMaxPower")) {
+    if let Ok(s) = root.read_to_string(dir.join("bMaxPower")) {
         dev.max_power = s.trim().to_owned();
     } else {
         log::warn!("Failed to read usb max power file at: {dir:?}");
     }
-    if let Ok(s) = fs::read_to_string(dir.join("manufacturer")) {
+    if let Ok(s) = root.read_to_string(dir.join("manufacturer")) {
         dev.manufacturer = s.trim().to_owned();
     } else {
         log::warn!("Failed to read usb manufacturer file at: {dir:?}");
     }
-    if let Ok(s) = fs::read_to_string(dir.join("product")) {
+    if let Ok(s) = root.read_to_string(dir.join("product")) {
         dev.product = s.trim().to_owned();
     } else {
         log::warn!("Failed to read usb product file at: {dir:?}");
     }
-    if let Ok(s) = fs::read_to_string(dir.join("speed")) {
+    if let Ok(s) = root.read_to_string(dir.join("speed")) {
         dev.speed = s.trim().to_owned();
     } else {
         log::warn!("Failed to read usb speed file at: {dir:?}");
     }
-    if let Ok(s) = fs::read_to_string(dir.join("version")) {
+    if let Ok(s) = root.read_to_string(dir.join("version")) {
         dev.version = s.trim().to_owned();
     } else {
         log::warn!("Failed to read usb version file at: {dir:?}");
     }
 
-    let uevent_content =
-        fs::read_to_string(dir.join("uevent")).map_err(|err| Error::IoError("usb uevent", err))?;
+    let uevent_content = root.read_to_string(dir.join("uevent"))?;
     for line in uevent_content.lines() {
         if !line.contains('=') {
             continue;
@@ -124,7 +123,7 @@ pub fn scan_usb_event(dir: &Path) -> Result<UsbDev, Error> {
     }
 
     let path = format!("/run/udev/data/c{}:{}", dev.major, dev.minor);
-    let udev_content = fs::read_to_string(&path).map_err(|err| Error::IoErrorDetail(path, err))?;
+    let udev_content = root.read_to_string(path)?;
 
     for line in udev_content.lines() {
         if !line.starts_with("E:") || !line.contains('=') {
@@ -169,11 +168,24 @@ pub fn scan_usb_event(dir: &Path) -> Result<UsbDev, Error> {
 #[cfg(test)]
 mod tests {
     use super::scan_usb;
+    use crate::base::system_root::SystemRoot;
 
     #[test]
     fn test_scan_usb() {
-        let list = scan_usb();
+        let list = scan_usb(&SystemRoot::fixture("laptop"));
         assert!(list.is_ok());
-        assert!(list.unwrap().len() > 2);
+        let list = list.unwrap();
+        assert_eq!(list.len(), 2);
+
+        let receiver = &list[0];
+        assert_eq!(receiver.product, "USB Receiver");
+        assert_eq!(receiver.bus_num, "001");
+        assert_eq!(receiver.dev_num, "002");
+        assert_eq!(receiver.vendor_id, "046d");
+        assert_eq!(receiver.model_from_database, "Unifying Receiver");
+
+        let hub = &list[1];
+        assert_eq!(hub.manufacturer, "Linux 6.1.0 xhci-hcd");
+        assert_eq!(hub.vendor_from_database, "Linux Foundation");
     }
 }
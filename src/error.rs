@@ -2,19 +2,33 @@
 // Use of this source is governed by General Public License that can be found
 // in the LICENSE file.
 
+use std::fmt;
 use std::io;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
 pub enum Error {
-    #[error("IoError to read file: `{0}`, reason: `{1}`")]
-    IoError(&'static str, io::Error),
+    #[error("File not present: `{}`", path.display())]
+    NotPresent { path: PathBuf, source: io::Error },
 
-    #[error("IoErrorDetail to read: `{0}`, reason: `{1}`")]
-    IoErrorDetail(String, io::Error),
+    #[error("Permission denied to read: `{}`", path.display())]
+    PermissionDenied { path: PathBuf, source: io::Error },
 
-    #[error("Failed to parse `{0}`, reason: `{1}`")]
-    ParseFile(&'static str, &'static str),
+    #[error("IoError to read: `{}`, reason: `{source}`", path.display())]
+    IoError { path: PathBuf, source: io::Error },
+
+    #[error("Syscall `{name}` failed, reason: `{source}`")]
+    Syscall {
+        name: &'static str,
+        source: io::Error,
+    },
+
+    #[error(transparent)]
+    Malformed(#[from] ParseError),
+
+    #[error("Failed to process snapshot archive, reason: `{0}`")]
+    Archive(io::Error),
 
     #[error("Failed to find `{0}`")]
     NotFound(String),
@@ -25,3 +39,141 @@ pub enum Error {
     #[error("Failed to parse address")]
     AddrParseError(#[from] std::net::AddrParseError),
 }
+
+/// Category of error, so that callers can decide which errors to skip.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ErrorKind {
+    /// File or directory does not exist.
+    NotPresent,
+
+    /// No permission to read file or directory.
+    PermissionDenied,
+
+    /// File content is invalid.
+    Malformed,
+
+    Other,
+}
+
+/// Details of content which failed to parse.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ParseError {
+    /// Path of file in target system.
+    pub path: PathBuf,
+
+    /// Line number starts from 1, `None` if file is not line based.
+    pub line: Option<usize>,
+
+    /// Name of field being parsed.
+    pub field: &'static str,
+
+    /// Raw text which failed to parse.
+    pub text: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Failed to parse `{}` in `{}",
+            self.field,
+            self.path.display()
+        )?;
+        if let Some(line) = self.line {
+            write!(f, ":{line}")?;
+        }
+        write!(f, "`, text: `{}`", self.text)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl Error {
+    /// Create error from `io::Error`, which happened when reading `path`.
+    pub fn from_io<P: Into<PathBuf>>(path: P, source: io::Error) -> Self {
+        let path = path.into();
+        match source.kind() {
+            io::ErrorKind::NotFound => Self::NotPresent { path, source },
+            io::ErrorKind::PermissionDenied => Self::PermissionDenied { path, source },
+            _ => Self::IoError { path, source },
+        }
+    }
+
+    /// Create error from `errno` returned by syscall `name`.
+    #[must_use]
+    pub fn syscall(name: &'static str, errno: i32) -> Self {
+        Self::Syscall {
+            name,
+            source: io::Error::from_raw_os_error(errno),
+        }
+    }
+
+    /// Create error of `field` which failed to parse.
+    pub fn malformed<P: AsRef<Path>>(
+        path: P,
+        line: Option<usize>,
+        field: &'static str,
+        text: &str,
+    ) -> Self {
+        Self::Malformed(ParseError {
+            path: path.as_ref().to_path_buf(),
+            line,
+            field,
+            text: text.to_owned(),
+        })
+    }
+
+    #[must_use]
+    pub const fn kind(&self) -> ErrorKind {
+        match self {
+            Self::NotPresent { .. } => ErrorKind::NotPresent,
+            Self::PermissionDenied { .. } => ErrorKind::PermissionDenied,
+            Self::Malformed(_) => ErrorKind::Malformed,
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::{Error, ErrorKind};
+
+    #[test]
+    fn test_kind() {
+        let err = Error::from_io("/proc/modules", io::Error::from(io::ErrorKind::NotFound));
+        assert_eq!(err.kind(), ErrorKind::NotPresent);
+        let err = Error::from_io(
+            "/etc/gshadow",
+            io::Error::from(io::ErrorKind::PermissionDenied),
+        );
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+        let err = Error::from_io("/proc/modules", io::Error::from(io::ErrorKind::Interrupted));
+        assert_eq!(err.kind(), ErrorKind::Other);
+    }
+
+    #[test]
+    fn test_syscall() {
+        let err = Error::syscall("clock_gettime", 22);
+        assert_eq!(err.kind(), ErrorKind::Other);
+        assert!(err
+            .to_string()
+            .starts_with("Syscall `clock_gettime` failed, reason: `Invalid argument"));
+    }
+
+    #[test]
+    fn test_malformed() {
+        let err = Error::malformed("/proc/net/route", Some(3), "metric", "abc");
+        assert_eq!(err.kind(), ErrorKind::Malformed);
+        assert_eq!(
+            err.to_string(),
+            "Failed to parse `metric` in `/proc/net/route:3`, text: `abc`"
+        );
+        let err = Error::malformed("/proc/uptime", None, "uptime", "");
+        assert_eq!(
+            err.to_string(),
+            "Failed to parse `uptime` in `/proc/uptime`, text: ``"
+        );
+    }
+}
@@ -15,4 +15,7 @@ pub mod base;
 pub mod computer;
 pub mod device;
 pub mod error;
+pub mod metrics;
 pub mod network;
+pub mod report;
+pub mod snapshot;
@@ -0,0 +1,373 @@
+// Copyright (c) 2023 Xu Shaohua <shaohua@biofan.org>. All rights reserved.
+// Use of this source is governed by General Public License that can be found
+// in the LICENSE file.
+
+//! Export numeric info as `OpenMetrics` text, which can be scraped by Prometheus.
+
+use std::fmt::{self, Write};
+
+use crate::base::system_root::SystemRoot;
+use crate::computer::{module, uptime};
+use crate::device::power_supply;
+use crate::error::Error;
+use crate::network::dev;
+
+/// Content type of `OpenMetrics` text exposition format.
+pub const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+const PREFIX: &str = "xingtian";
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum MetricType {
+    Counter,
+    Gauge,
+}
+
+impl fmt::Display for MetricType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Counter => write!(f, "counter"),
+            Self::Gauge => write!(f, "gauge"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Value {
+    Int(i64),
+    Float(f64),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Int(value) => write!(f, "{value}"),
+            Self::Float(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Sample {
+    labels: Vec<(&'static str, String)>,
+    value: Value,
+}
+
+#[derive(Debug, Clone)]
+struct Family {
+    name: &'static str,
+    help: &'static str,
+    type_: MetricType,
+    unit: Option<&'static str>,
+    samples: Vec<Sample>,
+}
+
+impl Family {
+    const fn new(
+        name: &'static str,
+        help: &'static str,
+        type_: MetricType,
+        unit: Option<&'static str>,
+    ) -> Self {
+        Self {
+            name,
+            help,
+            type_,
+            unit,
+            samples: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, labels: Vec<(&'static str, String)>, value: Value) {
+        self.samples.push(Sample { labels, value });
+    }
+
+    fn render(&self, out: &mut String) {
+        let name = format!("{PREFIX}_{}", self.name);
+        let _ = writeln!(out, "# TYPE {name} {}", self.type_);
+        if let Some(unit) = self.unit {
+            let _ = writeln!(out, "# UNIT {name} {unit}");
+        }
+        let _ = writeln!(out, "# HELP {name} {}", self.help);
+
+        let suffix = if self.type_ == MetricType::Counter {
+            "_total"
+        } else {
+            ""
+        };
+        for sample in &self.samples {
+            let _ = write!(out, "{name}{suffix}");
+            if !sample.labels.is_empty() {
+                let labels: Vec<String> = sample
+                    .labels
+                    .iter()
+                    .map(|(key, value)| format!("{key}=\"{}\"", escape_label(value)))
+                    .collect();
+                let _ = write!(out, "{{{}}}", labels.join(","));
+            }
+            let _ = writeln!(out, " {}", sample.value);
+        }
+    }
+}
+
+fn escape_label(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Convert micro unit in sysfs to base unit, like µV to V.
+fn from_micro(value: i32) -> Value {
+    Value::Float(f64::from(value) / 1_000_000.0)
+}
+
+fn network_families(list: &[dev::Dev]) -> Vec<Family> {
+    type Field = fn(&dev::Dev) -> i64;
+    let counters: [(&'static str, &'static str, Option<&'static str>, Field); 8] = [
+        (
+            "network_receive_bytes",
+            "Bytes received by network interface.",
+            Some("bytes"),
+            |d| d.received_bytes,
+        ),
+        (
+            "network_receive_packets",
+            "Packets received by network interface.",
+            None,
+            |d| d.received_packets,
+        ),
+        (
+            "network_receive_errors",
+            "Receive errors of network interface.",
+            None,
+            |d| d.received_errors,
+        ),
+        (
+            "network_receive_drop",
+            "Received packets dropped by network interface.",
+            None,
+            |d| d.received_drop,
+        ),
+        (
+            "network_transmit_bytes",
+            "Bytes transmitted by network interface.",
+            Some("bytes"),
+            |d| d.transmit_bytes,
+        ),
+        (
+            "network_transmit_packets",
+            "Packets transmitted by network interface.",
+            None,
+            |d| d.transmit_packets,
+        ),
+        (
+            "network_transmit_errors",
+            "Transmit errors of network interface.",
+            None,
+            |d| d.transmit_errors,
+        ),
+        (
+            "network_transmit_drop",
+            "Transmitted packets dropped by network interface.",
+            None,
+            |d| d.transmit_drop,
+        ),
+    ];
+
+    counters
+        .iter()
+        .map(|(name, help, unit, field)| {
+            let mut family = Family::new(name, help, MetricType::Counter, *unit);
+            for dev in list {
+                family.add(
+                    vec![("interface", dev.interface.clone())],
+                    Value::Int(field(dev)),
+                );
+            }
+            family
+        })
+        .collect()
+}
+
+fn power_supply_families(list: &[power_supply::PowerSupply]) -> Vec<Family> {
+    type Field = fn(&power_supply::PowerSupply) -> Value;
+    let gauges: [(&'static str, &'static str, Option<&'static str>, Field); 7] = [
+        (
+            "power_supply_voltage_volts",
+            "Present voltage of power supply.",
+            Some("volts"),
+            |ps| from_micro(ps.voltage_now),
+        ),
+        (
+            "power_supply_current_amperes",
+            "Present current of power supply.",
+            Some("amperes"),
+            |ps| from_micro(ps.current_now),
+        ),
+        (
+            "power_supply_charge_ampere_hours",
+            "Present charge of power supply.",
+            Some("ampere_hours"),
+            |ps| from_micro(ps.charge_now),
+        ),
+        (
+            "power_supply_charge_full_ampere_hours",
+            "Charge of power supply when fully charged.",
+            Some("ampere_hours"),
+            |ps| from_micro(ps.charge_full),
+        ),
+        (
+            "power_supply_charge_full_design_ampere_hours",
+            "Design charge of power supply when fully charged.",
+            Some("ampere_hours"),
+            |ps| from_micro(ps.charge_full_design),
+        ),
+        (
+            "power_supply_capacity_ratio",
+            "Capacity of power supply, from 0 to 1.",
+            Some("ratio"),
+            |ps| Value::Float(f64::from(ps.capacity) / 100.0),
+        ),
+        (
+            "power_supply_cycle_count",
+            "Charge cycles of power supply.",
+            None,
+            |ps| Value::Int(i64::from(ps.cycle_count)),
+        ),
+    ];
+
+    gauges
+        .iter()
+        .map(|(name, help, unit, field)| {
+            let mut family = Family::new(name, help, MetricType::Gauge, *unit);
+            for ps in list {
+                family.add(vec![("name", ps.name.clone())], field(ps));
+            }
+            family
+        })
+        .collect()
+}
+
+fn module_family(list: &[module::Module]) -> Family {
+    let mut family = Family::new(
+        "module_memory_bytes",
+        "Memory used by kernel module.",
+        MetricType::Gauge,
+        Some("bytes"),
+    );
+    for module in list {
+        family.add(
+            vec![("module", module.name.clone())],
+            Value::Int(i64::try_from(module.memory_bytes).unwrap_or(i64::MAX)),
+        );
+    }
+    family
+}
+
+fn add_status<T>(status: &mut Family, collector: &'static str, result: &Result<T, Error>) {
+    if let Err(err) = result {
+        log::warn!("Failed to collect {collector} metrics: {err}");
+    }
+    status.add(
+        vec![("collector", collector.to_owned())],
+        Value::Int(i64::from(result.is_ok())),
+    );
+}
+
+/// Collect metrics from `root` and render them as `OpenMetrics` text.
+///
+/// Failed collectors are skipped and reported by `xingtian_collector_success`.
+#[must_use]
+pub fn gather(root: &SystemRoot) -> String {
+    let mut families = Vec::new();
+    let mut status = Family::new(
+        "collector_success",
+        "Whether the collector succeeded.",
+        MetricType::Gauge,
+        None,
+    );
+
+    let uptime = uptime::get_uptime(root);
+    add_status(&mut status, "uptime", &uptime);
+    if let Ok(uptime) = uptime {
+        let mut family = Family::new(
+            "uptime_seconds",
+            "Time since system boot.",
+            MetricType::Gauge,
+            Some("seconds"),
+        );
+        family.add(Vec::new(), Value::Float(uptime.as_secs_f64()));
+        families.push(family);
+    }
+
+    let modules = module::get_list(root);
+    add_status(&mut status, "modules", &modules);
+    if let Ok(modules) = modules {
+        families.push(module_family(&modules));
+    }
+
+    let power_supplies = power_supply::get_list(root);
+    add_status(&mut status, "power_supply", &power_supplies);
+    if let Ok(power_supplies) = power_supplies {
+        families.extend(power_supply_families(&power_supplies));
+    }
+
+    let devs = dev::get_list(root);
+    add_status(&mut status, "network_dev", &devs);
+    if let Ok(devs) = devs {
+        families.extend(network_families(&devs));
+    }
+
+    families.push(status);
+
+    let mut out = String::new();
+    for family in &families {
+        family.render(&mut out);
+    }
+    out.push_str("# EOF\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape_label, gather};
+    use crate::base::system_root::SystemRoot;
+
+    #[test]
+    fn test_escape_label() {
+        assert_eq!(escape_label("a\"b\\c\n"), "a\\\"b\\\\c\\n");
+    }
+
+    #[test]
+    fn test_gather() {
+        let text = gather(&SystemRoot::fixture("laptop"));
+        assert!(text.ends_with("# EOF\n"));
+        assert!(text.contains(
+            "# TYPE xingtian_network_receive_bytes counter\n\
+             # UNIT xingtian_network_receive_bytes bytes\n\
+             # HELP xingtian_network_receive_bytes Bytes received by network interface.\n\
+             xingtian_network_receive_bytes_total{interface=\"lo\"} 11680274\n\
+             xingtian_network_receive_bytes_total{interface=\"wlp2s0\"} 1834577204\n"
+        ));
+        assert!(text.contains("xingtian_power_supply_voltage_volts{name=\"BAT0\"} 12.421\n"));
+        assert!(text.contains("xingtian_power_supply_capacity_ratio{name=\"BAT0\"} 0.79\n"));
+        assert!(text.contains("xingtian_module_memory_bytes{module=\"bridge\"} 409600\n"));
+        assert!(text.contains("xingtian_uptime_seconds 12345.67\n"));
+        assert!(text.contains("xingtian_collector_success{collector=\"network_dev\"} 1\n"));
+    }
+
+    #[test]
+    fn test_gather_partial() {
+        let text = gather(&SystemRoot::fixture("partial"));
+        assert!(text.contains("xingtian_collector_success{collector=\"modules\"} 1\n"));
+        assert!(text.contains("xingtian_collector_success{collector=\"power_supply\"} 0\n"));
+        assert!(!text.contains("xingtian_network_receive_bytes"));
+    }
+}
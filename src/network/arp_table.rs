@@ -2,11 +2,12 @@
 // Use of this source is governed by General Public License that can be found
 // in the LICENSE file.
 
-use std::fs;
-
+use crate::base::parse_mode::{ParseMode, Parsed};
+use crate::base::system_root::SystemRoot;
 use crate::error::Error;
 
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Arp {
     pub ip: String,
     pub hw_type: String,
@@ -17,23 +18,26 @@ pub struct Arp {
     pub device: String,
 }
 
+const FILE: &str = "/proc/net/arp";
+
 /// # Errors
 /// Returns error if failed to parse arp file.
-pub fn get_list() -> Result<Vec<Arp>, Error> {
-    const FILE: &str = "/proc/net/arp";
-    let content = fs::read_to_string(FILE).map_err(|err| Error::IoError(FILE, err))?;
+pub fn get_list(root: &SystemRoot) -> Result<Vec<Arp>, Error> {
+    get_list_with_mode(root, ParseMode::Strict).map(|parsed| parsed.value)
+}
 
-    let mut list = Vec::new();
-    for line in content.lines() {
-        if line.starts_with("Iface") {
+/// Same as [`get_list`], but entries which failed to parse are skipped
+/// in lenient mode.
+///
+/// # Errors
+/// Returns error if failed to read arp file, or failed to parse it in strict mode.
+pub fn get_list_with_mode(root: &SystemRoot, mode: ParseMode) -> Result<Parsed<Vec<Arp>>, Error> {
+    let content = root.read_to_string(FILE)?;
+
+    let mut parsed = Parsed::<Vec<Arp>>::default();
+    for (index, line) in content.lines().enumerate() {
+        if line.starts_with("IP address") {
             // Found header line.
-            let parts = line.split('\t');
-            if parts.count() != 6 {
-                return Err(Error::ParseFile(
-                    FILE,
-                    "Failed to parse arp file, expect 6 parts",
-                ));
-            }
             continue;
         }
 
@@ -41,38 +45,54 @@ pub fn get_list() -> Result<Vec<Arp>, Error> {
             continue;
         }
 
-        let parts = line.split('\t');
-        let mut arp = Arp::default();
-        for (index, part) in parts.enumerate() {
-            match index {
-                0 => arp.ip = part.to_owned(),
-                1 => arp.hw_type = part.to_owned(),
-                2 => arp.flags = part.to_owned(),
-                3 => arp.hw_address = part.to_owned(),
-                4 => arp.mask = part.to_owned(),
-                5 => arp.device = part.to_owned(),
-                _ => {
-                    return Err(Error::ParseFile(
-                        FILE,
-                        "Failed to parse arp file, expect 6 parts",
-                    ));
-                }
-            }
+        if let Some(arp) = mode.check(parse_line(line, Some(index + 1)), &mut parsed.warnings)? {
+            parsed.value.push(arp);
         }
-        list.push(arp);
     }
 
-    Ok(list)
+    Ok(parsed)
+}
+
+fn parse_line(line: &str, line_num: Option<usize>) -> Result<Arp, Error> {
+    let parts = line.split_ascii_whitespace();
+    let mut arp = Arp::default();
+    for (index, part) in parts.enumerate() {
+        match index {
+            0 => arp.ip = part.to_owned(),
+            1 => arp.hw_type = part.to_owned(),
+            2 => arp.flags = part.to_owned(),
+            3 => arp.hw_address = part.to_owned(),
+            4 => arp.mask = part.to_owned(),
+            5 => arp.device = part.to_owned(),
+            _ => return Err(Error::malformed(FILE, line_num, "columns", line)),
+        }
+    }
+    Ok(arp)
 }
 
 #[cfg(test)]
 mod tests {
     use super::get_list;
+    use crate::base::system_root::SystemRoot;
 
     #[test]
     fn test_get_list() {
-        let list = get_list();
+        let list = get_list(&SystemRoot::default());
         assert!(list.is_ok());
         assert!(list.unwrap().len() >= 1);
     }
+
+    #[test]
+    fn test_get_list_fixture() {
+        let list = get_list(&SystemRoot::fixture("laptop"));
+        assert!(list.is_ok());
+        let list = list.unwrap();
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].ip, "192.168.1.1");
+        assert_eq!(list[0].hw_type, "0x1");
+        assert_eq!(list[0].flags, "0x2");
+        assert_eq!(list[0].hw_address, "a4:91:b1:3c:5e:01");
+        assert_eq!(list[0].mask, "*");
+        assert_eq!(list[0].device, "wlp2s0");
+    }
 }
@@ -2,12 +2,15 @@
 // Use of this source is governed by General Public License that can be found
 // in the LICENSE file.
 
-use std::fs;
+use std::path::Path;
 
+use crate::base::parse_mode::{ParseMode, Parsed};
+use crate::base::system_root::SystemRoot;
 use crate::error::Error;
 
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DevType {
     Unknown = 0,
     Loopback = 1,
@@ -27,6 +30,7 @@ impl Default for DevType {
 }
 
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Dev {
     pub interface: String,
 
@@ -105,128 +109,195 @@ impl DevType {
     }
 }
 
+const FILE: &str = "/proc/net/dev";
+
 /// # Errors
 /// Returns error if failed to parse dev file.
-#[allow(clippy::too_many_lines)]
-pub fn get_list() -> Result<Vec<Dev>, Error> {
-    const FILE: &str = "/proc/net/dev";
-    let content = fs::read_to_string(FILE).map_err(|err| Error::IoError(FILE, err))?;
+pub fn get_list(root: &SystemRoot) -> Result<Vec<Dev>, Error> {
+    get_list_with_mode(root, ParseMode::Strict).map(|parsed| parsed.value)
+}
+
+/// Same as [`get_list`], but interfaces which failed to parse are skipped
+/// in lenient mode.
+///
+/// # Errors
+/// Returns error if failed to read dev file, or failed to parse it in strict mode.
+pub fn get_list_with_mode(root: &SystemRoot, mode: ParseMode) -> Result<Parsed<Vec<Dev>>, Error> {
+    let content = root.read_to_string(FILE)?;
 
-    let mut list = Vec::new();
+    let mut parsed = Parsed::<Vec<Dev>>::default();
 
-    for line in content.lines() {
+    for (index, line) in content.lines().enumerate() {
         // header line
         if line.contains('|') {
             continue;
         }
-        let parts = line.split_ascii_whitespace();
-        let mut dev = Dev::default();
-        for (index, part) in parts.enumerate() {
-            match index {
-                0 => {
-                    dev.interface = part.trim().to_owned();
-                    dev.dev_type = DevType::from_name(&dev.interface);
-                }
-                1 => {
-                    dev.received_bytes = part
-                        .trim()
-                        .parse()
-                        .map_err(|_err| Error::ParseFile(FILE, "Failed to parse received bytes"))?;
-                }
-                2 => {
-                    dev.received_packets = part.trim().parse().map_err(|_err| {
-                        Error::ParseFile(FILE, "Failed to parse received packets")
-                    })?;
-                }
-                3 => {
-                    dev.received_errors = part.trim().parse().map_err(|_err| {
-                        Error::ParseFile(FILE, "Failed to parse received errors")
-                    })?;
-                }
-                4 => {
-                    dev.received_drop = part
-                        .trim()
-                        .parse()
-                        .map_err(|_err| Error::ParseFile(FILE, "Failed to parse received drop"))?;
-                }
-                5 => {
-                    dev.received_fifo = part
-                        .trim()
-                        .parse()
-                        .map_err(|_err| Error::ParseFile(FILE, "Failed to parse received fifo"))?;
-                }
-                6 => {
-                    dev.received_frame = part
-                        .trim()
-                        .parse()
-                        .map_err(|_err| Error::ParseFile(FILE, "Failed to parse received frame"))?;
-                }
-                7 => {
-                    dev.received_compressed = part.trim().parse().map_err(|_err| {
-                        Error::ParseFile(FILE, "Failed to parse received compressed")
-                    })?;
-                }
-                8 => {
-                    dev.received_multicast = part.trim().parse().map_err(|_err| {
-                        Error::ParseFile(FILE, "Failed to parse received multicast")
-                    })?;
-                }
-                9 => {
-                    dev.transmit_bytes = part
-                        .trim()
-                        .parse()
-                        .map_err(|_err| Error::ParseFile(FILE, "Failed to parse transmit bytes"))?;
-                }
-                10 => {
-                    dev.transmit_packets = part.trim().parse().map_err(|_err| {
-                        Error::ParseFile(FILE, "Failed to parse transmit packets")
-                    })?;
-                }
-                11 => {
-                    dev.transmit_errors = part.trim().parse().map_err(|_err| {
-                        Error::ParseFile(FILE, "Failed to parse transmit errors")
-                    })?;
-                }
-                12 => {
-                    dev.transmit_drop = part
-                        .trim()
-                        .parse()
-                        .map_err(|_err| Error::ParseFile(FILE, "Failed to parse transmit drop"))?;
-                }
-                13 => {
-                    dev.transmit_fifo = part
-                        .trim()
-                        .parse()
-                        .map_err(|_err| Error::ParseFile(FILE, "Failed to parse transmit fifo"))?;
-                }
-                14 => {
-                    dev.transmit_colls = part
-                        .trim()
-                        .parse()
-                        .map_err(|_err| Error::ParseFile(FILE, "Failed to parse transmit colls"))?;
-                }
-                15 => {
-                    dev.transmit_carrier = part.trim().parse().map_err(|_err| {
-                        Error::ParseFile(FILE, "Failed to parse transmit carrier")
-                    })?;
-                }
-                16 => {
-                    dev.transmit_compressed = part.trim().parse().map_err(|_err| {
-                        Error::ParseFile(FILE, "Failed to parse transmit compressed")
-                    })?;
-                }
-                _ => return Err(Error::ParseFile(FILE, "Too many parts in dev file")),
+        let result = parse_line(root, line, Some(index + 1));
+        if let Some(dev) = mode.check(result, &mut parsed.warnings)? {
+            parsed.value.push(dev);
+        }
+    }
+
+    Ok(parsed)
+}
+
+#[allow(clippy::too_many_lines)]
+fn parse_line(root: &SystemRoot, line: &str, line_num: Option<usize>) -> Result<Dev, Error> {
+    let parts = line.split_ascii_whitespace();
+    let mut dev = Dev::default();
+    for (index, part) in parts.enumerate() {
+        match index {
+            0 => {
+                dev.interface = part.trim().trim_end_matches(':').to_owned();
+                dev.dev_type = DevType::from_name(&dev.interface);
+            }
+            1 => {
+                dev.received_bytes = part
+                    .trim()
+                    .parse()
+                    .map_err(|_err| Error::malformed(FILE, line_num, "received_bytes", part))?;
+            }
+            2 => {
+                dev.received_packets = part
+                    .trim()
+                    .parse()
+                    .map_err(|_err| Error::malformed(FILE, line_num, "received_packets", part))?;
+            }
+            3 => {
+                dev.received_errors = part
+                    .trim()
+                    .parse()
+                    .map_err(|_err| Error::malformed(FILE, line_num, "received_errors", part))?;
+            }
+            4 => {
+                dev.received_drop = part
+                    .trim()
+                    .parse()
+                    .map_err(|_err| Error::malformed(FILE, line_num, "received_drop", part))?;
+            }
+            5 => {
+                dev.received_fifo = part
+                    .trim()
+                    .parse()
+                    .map_err(|_err| Error::malformed(FILE, line_num, "received_fifo", part))?;
+            }
+            6 => {
+                dev.received_frame = part
+                    .trim()
+                    .parse()
+                    .map_err(|_err| Error::malformed(FILE, line_num, "received_frame", part))?;
+            }
+            7 => {
+                dev.received_compressed = part.trim().parse().map_err(|_err| {
+                    Error::malformed(FILE, line_num, "received_compressed", part)
+                })?;
             }
+            8 => {
+                dev.received_multicast = part
+                    .trim()
+                    .parse()
+                    .map_err(|_err| Error::malformed(FILE, line_num, "received_multicast", part))?;
+            }
+            9 => {
+                dev.transmit_bytes = part
+                    .trim()
+                    .parse()
+                    .map_err(|_err| Error::malformed(FILE, line_num, "transmit_bytes", part))?;
+            }
+            10 => {
+                dev.transmit_packets = part
+                    .trim()
+                    .parse()
+                    .map_err(|_err| Error::malformed(FILE, line_num, "transmit_packets", part))?;
+            }
+            11 => {
+                dev.transmit_errors = part
+                    .trim()
+                    .parse()
+                    .map_err(|_err| Error::malformed(FILE, line_num, "transmit_errors", part))?;
+            }
+            12 => {
+                dev.transmit_drop = part
+                    .trim()
+                    .parse()
+                    .map_err(|_err| Error::malformed(FILE, line_num, "transmit_drop", part))?;
+            }
+            13 => {
+                dev.transmit_fifo = part
+                    .trim()
+                    .parse()
+                    .map_err(|_err| Error::malformed(FILE, line_num, "transmit_fifo", part))?;
+            }
+            14 => {
+                dev.transmit_colls = part
+                    .trim()
+                    .parse()
+                    .map_err(|_err| Error::malformed(FILE, line_num, "transmit_colls", part))?;
+            }
+            15 => {
+                dev.transmit_carrier = part
+                    .trim()
+                    .parse()
+                    .map_err(|_err| Error::malformed(FILE, line_num, "transmit_carrier", part))?;
+            }
+            16 => {
+                dev.transmit_compressed = part.trim().parse().map_err(|_err| {
+                    Error::malformed(FILE, line_num, "transmit_compressed", part)
+                })?;
+            }
+            _ => return Err(Error::malformed(FILE, line_num, "columns", line)),
         }
+    }
+
+    get_ip_info(root, &mut dev)?;
 
-        get_ip_info(&mut dev)?;
+    Ok(dev)
+}
 
-        list.push(dev);
+fn get_ip_info(root: &SystemRoot, dev: &mut Dev) -> Result<(), Error> {
+    let dir = Path::new("/sys/class/net").join(&dev.interface);
+
+    if let Ok(s) = root.read_to_string(dir.join("address")) {
+        dev.mac = s.trim().to_owned();
+    } else {
+        log::warn!("Failed to read mac address of {}", dev.interface);
+    }
+    if let Ok(s) = root.read_to_string(dir.join("mtu")) {
+        dev.mtu = s
+            .trim()
+            .parse()
+            .map_err(|_err| Error::malformed(dir.join("mtu"), Some(1), "mtu", &s))?;
+    } else {
+        log::warn!("Failed to read mtu of {}", dev.interface);
     }
+    // TODO(Shaohua): Read ip, mask and broadcast address.
 
-    Ok(list)
+    Ok(())
 }
 
-fn get_ip_info(_dev: &mut Dev) -> Result<(), Error> {
-    todo!()
+#[cfg(test)]
+mod tests {
+    use super::{get_list, DevType};
+    use crate::base::system_root::SystemRoot;
+
+    #[test]
+    fn test_get_list() {
+        let list = get_list(&SystemRoot::fixture("laptop"));
+        assert!(list.is_ok());
+        let list = list.unwrap();
+        assert_eq!(list.len(), 2);
+
+        let lo = &list[0];
+        assert_eq!(lo.interface, "lo");
+        assert_eq!(lo.dev_type, DevType::Loopback);
+        assert_eq!(lo.mtu, 65536);
+
+        let wlan = &list[1];
+        assert_eq!(wlan.interface, "wlp2s0");
+        assert_eq!(wlan.dev_type, DevType::Wireless);
+        assert_eq!(wlan.received_bytes, 1_834_577_204);
+        assert_eq!(wlan.transmit_packets, 612_377);
+        assert_eq!(wlan.mac, "a4:91:b1:3c:5e:7d");
+        assert_eq!(wlan.mtu, 1500);
+    }
 }
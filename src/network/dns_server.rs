@@ -3,11 +3,13 @@
 // in the LICENSE file.
 
 use dns_lookup::getnameinfo;
-use std::fs;
 use std::net::{IpAddr, SocketAddr};
 
+use crate::base::system_root::SystemRoot;
 use crate::error::Error;
 
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DnsServer {
     pub ip: String,
     pub hostname: String,
@@ -15,17 +17,17 @@ pub struct DnsServer {
 
 /// # Errors
 /// Returns error if failed to parse resolv file, or failed to retrieve dns.
-pub fn get_list() -> Result<Vec<DnsServer>, Error> {
+pub fn get_list(root: &SystemRoot) -> Result<Vec<DnsServer>, Error> {
     const FILE: &str = "/etc/resolv.conf";
-    let content = fs::read_to_string(FILE).map_err(|err| Error::IoError(FILE, err))?;
+    let content = root.read_to_string(FILE)?;
     let mut list = Vec::new();
 
-    for line in content.lines() {
+    for (index, line) in content.lines().enumerate() {
         let mut iter = line.split_ascii_whitespace();
         if iter.next() == Some("nameserver") {
             let domain = iter
                 .next()
-                .ok_or_else(|| Error::ParseFile(FILE, "Invalid nameserver"))?
+                .ok_or_else(|| Error::malformed(FILE, Some(index + 1), "nameserver", line))?
                 .trim();
             let hostname = get_hostname_by_ip(domain)?;
             list.push(DnsServer {
@@ -52,14 +54,24 @@ pub fn get_hostname_by_ip(addr: &str) -> Result<String, Error> {
 #[cfg(test)]
 mod tests {
     use super::{get_hostname_by_ip, get_list};
+    use crate::base::system_root::SystemRoot;
 
     #[test]
     fn test_get_list() {
-        let list = get_list();
+        let list = get_list(&SystemRoot::default());
         assert!(list.is_ok());
         assert!(list.unwrap().len() >= 1);
     }
 
+    #[test]
+    fn test_get_list_fixture() {
+        let list = get_list(&SystemRoot::fixture("laptop"));
+        assert!(list.is_ok());
+        let list = list.unwrap();
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].ip, "127.0.0.1");
+    }
+
     #[test]
     fn test_get_hostname_by_ip() {
         let hostname = get_hostname_by_ip("8.8.8.8");
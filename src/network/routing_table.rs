@@ -2,11 +2,14 @@
 // Use of this source is governed by General Public License that can be found
 // in the LICENSE file.
 
-use std::fs;
+use std::net::Ipv4Addr;
 
+use crate::base::parse_mode::{ParseMode, Parsed};
+use crate::base::system_root::SystemRoot;
 use crate::error::Error;
 
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Routing {
     pub interface: String,
     pub destination: String,
@@ -23,22 +26,34 @@ pub struct Routing {
     pub irtt: i32,
 }
 
+const FILE: &str = "/proc/net/route";
+
 /// # Errors
 /// Returns error if failed to parse route file.
-pub fn get_list() -> Result<Vec<Routing>, Error> {
-    const FILE: &str = "/proc/net/route";
-    let content = fs::read_to_string(FILE).map_err(|err| Error::IoError(FILE, err))?;
+pub fn get_list(root: &SystemRoot) -> Result<Vec<Routing>, Error> {
+    get_list_with_mode(root, ParseMode::Strict).map(|parsed| parsed.value)
+}
+
+/// Same as [`get_list`], but routes which failed to parse are skipped
+/// in lenient mode.
+///
+/// # Errors
+/// Returns error if failed to read route file or its header is invalid,
+/// or failed to parse any route in strict mode.
+pub fn get_list_with_mode(
+    root: &SystemRoot,
+    mode: ParseMode,
+) -> Result<Parsed<Vec<Routing>>, Error> {
+    let content = root.read_to_string(FILE)?;
 
-    let mut list = Vec::new();
-    for line in content.lines() {
+    let mut parsed = Parsed::<Vec<Routing>>::default();
+    for (index, line) in content.lines().enumerate() {
+        let line_num = Some(index + 1);
         if line.starts_with("Iface") {
             // Found header line.
             let parts = line.split_ascii_whitespace();
             if parts.count() != 11 {
-                return Err(Error::ParseFile(
-                    FILE,
-                    "Failed to parse route file, expect 11 parts",
-                ));
+                return Err(Error::malformed(FILE, line_num, "header", line));
             }
             continue;
         }
@@ -47,67 +62,134 @@ pub fn get_list() -> Result<Vec<Routing>, Error> {
             continue;
         }
 
-        let parts = line.split_ascii_whitespace();
-        let mut routing = Routing::default();
-        for (index, part) in parts.enumerate() {
-            match index {
-                0 => routing.interface = part.to_owned(),
-                1 => routing.destination = part.to_owned(),
-                2 => routing.gateway = part.to_owned(),
-                3 => routing.flags = part.to_owned(),
-                4 => {
-                    routing.refcnt = part
-                        .parse()
-                        .map_err(|_err| Error::ParseFile(FILE, "Failed to parse refcnt"))?;
-                }
-                5 => {
-                    routing.used = part
-                        .parse()
-                        .map_err(|_err| Error::ParseFile(FILE, "Failed to parse used"))?;
-                }
-                6 => {
-                    routing.metric = part
-                        .parse()
-                        .map_err(|_err| Error::ParseFile(FILE, "Failed to parse metric"))?;
-                }
-                7 => routing.mask = part.to_owned(),
-                8 => {
-                    routing.mtu = part
-                        .parse()
-                        .map_err(|_err| Error::ParseFile(FILE, "Failed to parse mtu"))?;
-                }
-                9 => {
-                    routing.window = part
-                        .parse()
-                        .map_err(|_err| Error::ParseFile(FILE, "Failed to parse window"))?;
-                }
-                10 => {
-                    routing.irtt = part
-                        .parse()
-                        .map_err(|_err| Error::ParseFile(FILE, "Failed to parse irtt"))?;
-                }
-                _ => {
-                    return Err(Error::ParseFile(
-                        FILE,
-                        "Failed to parse route file, expect 11 parts",
-                    ));
-                }
+        if let Some(routing) = mode.check(parse_line(line, line_num), &mut parsed.warnings)? {
+            parsed.value.push(routing);
+        }
+    }
+
+    Ok(parsed)
+}
+
+fn parse_line(line: &str, line_num: Option<usize>) -> Result<Routing, Error> {
+    let parts = line.split_ascii_whitespace();
+    let mut routing = Routing::default();
+    for (index, part) in parts.enumerate() {
+        match index {
+            0 => routing.interface = part.to_owned(),
+            1 => routing.destination = part.to_owned(),
+            2 => routing.gateway = part.to_owned(),
+            3 => routing.flags = part.to_owned(),
+            4 => {
+                routing.refcnt = part
+                    .parse()
+                    .map_err(|_err| Error::malformed(FILE, line_num, "refcnt", part))?;
+            }
+            5 => {
+                routing.used = part
+                    .parse()
+                    .map_err(|_err| Error::malformed(FILE, line_num, "used", part))?;
+            }
+            6 => {
+                routing.metric = part
+                    .parse()
+                    .map_err(|_err| Error::malformed(FILE, line_num, "metric", part))?;
+            }
+            7 => routing.mask = part.to_owned(),
+            8 => {
+                routing.mtu = part
+                    .parse()
+                    .map_err(|_err| Error::malformed(FILE, line_num, "mtu", part))?;
             }
+            9 => {
+                routing.window = part
+                    .parse()
+                    .map_err(|_err| Error::malformed(FILE, line_num, "window", part))?;
+            }
+            10 => {
+                routing.irtt = part
+                    .parse()
+                    .map_err(|_err| Error::malformed(FILE, line_num, "irtt", part))?;
+            }
+            _ => return Err(Error::malformed(FILE, line_num, "columns", line)),
         }
-        list.push(routing);
     }
 
-    Ok(list)
+    Ok(routing)
+}
+
+/// Convert address in route file, like `0101A8C0`, to ipv4 address.
+///
+/// Addresses are hex strings in network byte order of little-endian host.
+#[must_use]
+pub fn hex_to_ipv4(s: &str) -> Option<Ipv4Addr> {
+    let num = u32::from_str_radix(s, 16).ok()?;
+    Some(Ipv4Addr::from(num.to_le_bytes()))
 }
 
 #[cfg(test)]
 mod tests {
-    use super::get_list;
+    use std::net::Ipv4Addr;
+
+    use std::path::Path;
+
+    use super::{get_list, get_list_with_mode, hex_to_ipv4};
+    use crate::base::parse_mode::ParseMode;
+    use crate::base::system_root::SystemRoot;
+    use crate::error::Error;
 
     #[test]
     fn test_get_list() {
-        let list = get_list();
+        let list = get_list(&SystemRoot::default());
         assert!(list.is_ok());
         assert!(list.unwrap().len() >= 1);
     }
+
+    #[test]
+    fn test_get_list_fixture() {
+        let list = get_list(&SystemRoot::fixture("laptop"));
+        assert!(list.is_ok());
+        let list = list.unwrap();
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].interface, "wlp2s0");
+        assert_eq!(list[0].gateway, "0101A8C0");
+        assert_eq!(list[0].metric, 600);
+        assert_eq!(list[1].mask, "00FFFFFF");
+    }
+
+    #[test]
+    fn test_get_list_malformed() {
+        let err = get_list(&SystemRoot::fixture("malformed")).unwrap_err();
+        let err = match err {
+            Error::Malformed(err) => err,
+            err => panic!("Expect malformed error, got: {err:?}"),
+        };
+        assert_eq!(err.path, Path::new("/proc/net/route"));
+        assert_eq!(err.line, Some(3));
+        assert_eq!(err.field, "metric");
+        assert_eq!(err.text, "abc");
+    }
+
+    #[test]
+    fn test_get_list_lenient() {
+        let parsed =
+            get_list_with_mode(&SystemRoot::fixture("malformed"), ParseMode::Lenient).unwrap();
+        assert_eq!(parsed.value.len(), 2);
+        assert_eq!(parsed.value[1].destination, "0002A8C0");
+        assert_eq!(parsed.warnings.len(), 1);
+        assert_eq!(
+            parsed.warnings[0].to_string(),
+            "Failed to parse `metric` in `/proc/net/route:3`, text: `abc`"
+        );
+    }
+
+    #[test]
+    fn test_hex_to_ipv4() {
+        assert_eq!(hex_to_ipv4("0101A8C0"), Some(Ipv4Addr::new(192, 168, 1, 1)));
+        assert_eq!(
+            hex_to_ipv4("00FFFFFF"),
+            Some(Ipv4Addr::new(255, 255, 255, 0))
+        );
+        assert_eq!(hex_to_ipv4("00000000"), Some(Ipv4Addr::UNSPECIFIED));
+        assert_eq!(hex_to_ipv4("xyz"), None);
+    }
 }
@@ -0,0 +1,309 @@
+// Copyright (c) 2023 Xu Shaohua <shaohua@biofan.org>. All rights reserved.
+// Use of this source is governed by General Public License that can be found
+// in the LICENSE file.
+
+//! Compare two reports of the same machine, taken at different times.
+
+use std::fmt;
+
+use crate::computer::module::Module;
+use crate::device::usb::UsbDev;
+use crate::network::dns_server::DnsServer;
+use crate::network::routing_table::{hex_to_ipv4, Routing};
+
+use super::{Section, SystemReport};
+
+/// Items which only exist in one of the reports.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Changes<T> {
+    /// Items only in new report.
+    pub added: Vec<T>,
+
+    /// Items only in old report.
+    pub removed: Vec<T>,
+}
+
+impl<T> Default for Changes<T> {
+    fn default() -> Self {
+        Self {
+            added: Vec::new(),
+            removed: Vec::new(),
+        }
+    }
+}
+
+impl<T> Changes<T> {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Full charge capacity of a battery in both reports.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BatteryDrift {
+    pub name: String,
+    pub old_charge_full: i32,
+    pub new_charge_full: i32,
+    pub charge_full_design: i32,
+}
+
+impl BatteryDrift {
+    /// Change of full charge capacity, in µAh.
+    #[must_use]
+    pub const fn delta(&self) -> i32 {
+        self.new_charge_full - self.old_charge_full
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReportDiff {
+    pub modules: Changes<Module>,
+    pub usb: Changes<UsbDev>,
+    pub routes: Changes<Routing>,
+    pub dns_servers: Changes<DnsServer>,
+
+    /// Batteries whose full charge capacity changed.
+    pub batteries: Vec<BatteryDrift>,
+
+    /// Sections not compared as they failed to collect in either report.
+    pub skipped: Vec<String>,
+}
+
+impl ReportDiff {
+    /// Compare `old` report with `new` one.
+    #[must_use]
+    pub fn new(old: &SystemReport, new: &SystemReport) -> Self {
+        let mut diff = Self::default();
+
+        if let Some((old, new)) = diff.both("modules", &old.computer.modules, &new.computer.modules)
+        {
+            diff.modules = changes(old, new, |m| m.name.clone());
+        }
+        if let Some((old, new)) = diff.both("usb", &old.device.usb, &new.device.usb) {
+            diff.usb = changes(old, new, usb_key);
+        }
+        if let Some((old, new)) = diff.both(
+            "routing_table",
+            &old.network.routing_table,
+            &new.network.routing_table,
+        ) {
+            diff.routes = changes(old, new, route_key);
+        }
+        if let Some((old, new)) = diff.both(
+            "dns_server",
+            &old.network.dns_server,
+            &new.network.dns_server,
+        ) {
+            diff.dns_servers = changes(old, new, |d| d.ip.clone());
+        }
+        if let Some((old, new)) = diff.both(
+            "power_supply",
+            &old.device.power_supply,
+            &new.device.power_supply,
+        ) {
+            diff.batteries = new
+                .iter()
+                .filter_map(|new_battery| {
+                    let old_battery = old.iter().find(|b| {
+                        b.name == new_battery.name && b.serial_number == new_battery.serial_number
+                    })?;
+                    (old_battery.charge_full != new_battery.charge_full).then(|| BatteryDrift {
+                        name: new_battery.name.clone(),
+                        old_charge_full: old_battery.charge_full,
+                        new_charge_full: new_battery.charge_full,
+                        charge_full_design: new_battery.charge_full_design,
+                    })
+                })
+                .collect();
+        }
+
+        diff
+    }
+
+    /// Returns true if nothing changed.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.modules.is_empty()
+            && self.usb.is_empty()
+            && self.routes.is_empty()
+            && self.dns_servers.is_empty()
+            && self.batteries.is_empty()
+    }
+
+    fn both<'a, T>(
+        &mut self,
+        name: &str,
+        old: &'a Section<T>,
+        new: &'a Section<T>,
+    ) -> Option<(&'a T, &'a T)> {
+        if let (Some(old), Some(new)) = (old.data(), new.data()) {
+            Some((old, new))
+        } else {
+            self.skipped.push(name.to_owned());
+            None
+        }
+    }
+}
+
+fn changes<T, K, F>(old: &[T], new: &[T], key: F) -> Changes<T>
+where
+    T: Clone,
+    K: PartialEq,
+    F: Fn(&T) -> K,
+{
+    let old_keys: Vec<K> = old.iter().map(&key).collect();
+    let new_keys: Vec<K> = new.iter().map(&key).collect();
+    Changes {
+        added: new
+            .iter()
+            .filter(|item| !old_keys.contains(&key(item)))
+            .cloned()
+            .collect(),
+        removed: old
+            .iter()
+            .filter(|item| !new_keys.contains(&key(item)))
+            .cloned()
+            .collect(),
+    }
+}
+
+/// Bus number and device number are reassigned on each plug, so usb device
+/// is identified by its id, serial and port.
+fn usb_key(dev: &UsbDev) -> (String, String, String, String) {
+    (
+        dev.vendor_id.clone(),
+        dev.model_id.clone(),
+        dev.serial.clone(),
+        dev.path.clone(),
+    )
+}
+
+fn route_key(route: &Routing) -> (String, String, String, String, i32) {
+    (
+        route.interface.clone(),
+        route.destination.clone(),
+        route.gateway.clone(),
+        route.mask.clone(),
+        route.metric,
+    )
+}
+
+fn ip(s: &str) -> String {
+    hex_to_ipv4(s).map_or_else(|| s.to_owned(), |ip| ip.to_string())
+}
+
+fn write_changes<T, F>(
+    f: &mut fmt::Formatter<'_>,
+    title: &str,
+    changes: &Changes<T>,
+    to_str: F,
+) -> fmt::Result
+where
+    F: Fn(&T) -> String,
+{
+    if changes.is_empty() {
+        return Ok(());
+    }
+    writeln!(f, "{title}:")?;
+    for item in &changes.added {
+        writeln!(f, "  + {}", to_str(item))?;
+    }
+    for item in &changes.removed {
+        writeln!(f, "  - {}", to_str(item))?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for ReportDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_changes(f, "Modules", &self.modules, |m| m.name.clone())?;
+        write_changes(f, "USB devices", &self.usb, |d| {
+            let product = if d.model_from_database.is_empty() {
+                &d.product
+            } else {
+                &d.model_from_database
+            };
+            format!("{}:{} {product}", d.vendor_id, d.model_id)
+        })?;
+        write_changes(f, "Routes", &self.routes, |r| {
+            format!(
+                "{}/{} via {} dev {} metric {}",
+                ip(&r.destination),
+                ip(&r.mask),
+                ip(&r.gateway),
+                r.interface,
+                r.metric
+            )
+        })?;
+        write_changes(f, "DNS servers", &self.dns_servers, |d| d.ip.clone())?;
+
+        if !self.batteries.is_empty() {
+            writeln!(f, "Batteries:")?;
+            for battery in &self.batteries {
+                writeln!(
+                    f,
+                    "  {} full charge {} -> {} ({:+})",
+                    battery.name,
+                    battery.old_charge_full,
+                    battery.new_charge_full,
+                    battery.delta()
+                )?;
+            }
+        }
+
+        for name in &self.skipped {
+            writeln!(f, "Skipped {name}: failed to collect")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ReportDiff;
+    use crate::base::system_root::SystemRoot;
+    use crate::computer::module::Module;
+    use crate::report::{Section, SystemReport};
+
+    #[test]
+    fn test_diff() {
+        let old = SystemReport::collect(&SystemRoot::fixture("laptop"));
+        assert!(ReportDiff::new(&old, &old).is_empty());
+
+        let mut new = old.clone();
+        if let Section::Data(modules) = &mut new.computer.modules {
+            modules.retain(|m| m.name != "stp");
+            modules.push(Module {
+                name: "nvidia".to_owned(),
+                memory_bytes: 1024,
+                ..Module::default()
+            });
+        }
+        if let Section::Data(usb) = &mut new.device.usb {
+            usb.retain(|d| d.vendor_id != "046d");
+        }
+        if let Section::Data(batteries) = &mut new.device.power_supply {
+            batteries[0].charge_full -= 12000;
+        }
+        new.network.dns_server = Section::Error("Failed".to_owned());
+
+        let diff = ReportDiff::new(&old, &new);
+        assert!(!diff.is_empty());
+        assert_eq!(diff.modules.added[0].name, "nvidia");
+        assert_eq!(diff.modules.removed[0].name, "stp");
+        assert!(diff.usb.added.is_empty());
+        assert_eq!(diff.usb.removed.len(), 1);
+        assert!(diff.routes.is_empty());
+        assert_eq!(diff.batteries[0].delta(), -12000);
+        assert_eq!(diff.skipped, ["dns_server"]);
+
+        let text = diff.to_string();
+        assert!(text.starts_with("Modules:\n  + nvidia\n  - stp\nUSB devices:\n  - 046d:c52b "));
+        assert!(text.contains("Batteries:\n  BAT0 full charge 3712000 -> 3700000 (-12000)\n"));
+        assert!(text.ends_with("Skipped dns_server: failed to collect\n"));
+    }
+}
@@ -0,0 +1,460 @@
+// Copyright (c) 2023 Xu Shaohua <shaohua@biofan.org>. All rights reserved.
+// Use of this source is governed by General Public License that can be found
+// in the LICENSE file.
+
+//! Format-independent layout of report, shared by html and markdown renderers.
+
+use crate::base::unit::{format_duration, format_time};
+use crate::computer::bootup::BootEnd;
+use crate::computer::vendor;
+use crate::network::routing_table::hex_to_ipv4;
+
+use super::{Section, SystemReport};
+
+#[derive(Debug, Clone)]
+pub struct Cell {
+    pub text: String,
+    pub link: Option<&'static str>,
+}
+
+impl Cell {
+    fn new<S: ToString + ?Sized>(text: &S) -> Self {
+        Self {
+            text: text.to_string(),
+            link: None,
+        }
+    }
+
+    /// Link vendor name to its homepage if it is found in vendor database.
+    fn vendor(name: &str) -> Self {
+        Self {
+            text: name.to_owned(),
+            link: vendor::get_vendor(name)
+                .map(|vendor| vendor.url)
+                .filter(|url| !url.is_empty()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Content {
+    Table {
+        headers: &'static [&'static str],
+        rows: Vec<Vec<Cell>>,
+    },
+    Error(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct Table {
+    pub title: &'static str,
+    pub content: Content,
+}
+
+#[derive(Debug, Clone)]
+pub struct Chapter {
+    pub title: &'static str,
+    pub tables: Vec<Table>,
+}
+
+fn table<T, F>(
+    title: &'static str,
+    headers: &'static [&'static str],
+    section: &Section<T>,
+    to_rows: F,
+) -> Table
+where
+    F: Fn(&T) -> Vec<Vec<Cell>>,
+{
+    let content = match section {
+        Section::Data(data) => Content::Table {
+            headers,
+            rows: to_rows(data),
+        },
+        Section::Error(err) => Content::Error(err.clone()),
+    };
+    Table { title, content }
+}
+
+fn ip_cell(s: &str) -> Cell {
+    hex_to_ipv4(s).map_or_else(|| Cell::new(s), |ip| Cell::new(&ip))
+}
+
+#[allow(clippy::too_many_lines)]
+pub fn build(report: &SystemReport) -> Vec<Chapter> {
+    let computer = Chapter {
+        title: "Computer",
+        tables: vec![
+            table(
+                "Operating System",
+                &["Item", "Value"],
+                &report.computer.os,
+                |os| {
+                    let kernel = &os.kernel;
+                    vec![
+                        vec![
+                            Cell::new("Distribution"),
+                            Cell::new(&os.release.pretty_name),
+                        ],
+                        vec![
+                            Cell::new("Kernel"),
+                            Cell::new(&format!("{} {}", kernel.release, kernel.machine)),
+                        ],
+                        vec![Cell::new("Hostname"), Cell::new(&os.hostname)],
+                        vec![Cell::new("Domain"), Cell::new(&os.domain)],
+                        vec![Cell::new("Desktop"), Cell::new(&os.desktop.desktop)],
+                        vec![
+                            Cell::new("Session type"),
+                            Cell::new(&os.desktop.session_type),
+                        ],
+                    ]
+                },
+            ),
+            table(
+                "Summary",
+                &["Item", "Value"],
+                &report.computer.uptime,
+                |uptime| {
+                    vec![vec![
+                        Cell::new("Uptime"),
+                        Cell::new(&format_duration(*uptime)),
+                    ]]
+                },
+            ),
+            table(
+                "Load",
+                &["Item", "Value"],
+                &report.computer.uptime_info,
+                |info| {
+                    let load = &info.load_average;
+                    vec![
+                        vec![
+                            Cell::new("Boot time"),
+                            Cell::new(&format_time(info.boot_time)),
+                        ],
+                        vec![
+                            Cell::new("Idle per CPU"),
+                            Cell::new(&format_duration(info.idle_per_cpu())),
+                        ],
+                        vec![
+                            Cell::new("Load average"),
+                            Cell::new(&format!(
+                                "{:.2} {:.2} {:.2}",
+                                load.one, load.five, load.fifteen
+                            )),
+                        ],
+                        vec![
+                            Cell::new("Tasks"),
+                            Cell::new(&format!("{} running, {} total", load.running, load.total)),
+                        ],
+                    ]
+                },
+            ),
+            table(
+                "Sessions",
+                &["User", "TTY", "From", "Login", "Idle", "PID"],
+                &report.computer.sessions,
+                |list| {
+                    list.iter()
+                        .map(|s| {
+                            vec![
+                                Cell::new(&s.user),
+                                Cell::new(&s.tty),
+                                Cell::new(&s.host),
+                                Cell::new(&format_time(s.login_time)),
+                                Cell::new(&s.idle.map(format_duration).unwrap_or_default()),
+                                Cell::new(&s.pid),
+                            ]
+                        })
+                        .collect()
+                },
+            ),
+            table(
+                "Failed Logins",
+                &[
+                    "Source",
+                    "Attempts",
+                    "Users",
+                    "First",
+                    "Last",
+                    "Brute force",
+                ],
+                &report.computer.failed_logins,
+                |list| {
+                    list.iter()
+                        .map(|a| {
+                            vec![
+                                Cell::new(&a.key),
+                                Cell::new(&a.count),
+                                Cell::new(&a.related.join(", ")),
+                                Cell::new(&format_time(a.first_time)),
+                                Cell::new(&format_time(a.last_time)),
+                                Cell::new(if a.is_brute_force() { "yes" } else { "" }),
+                            ]
+                        })
+                        .collect()
+                },
+            ),
+            table(
+                "Groups",
+                &["Name", "GID", "Members", "Admins"],
+                &report.computer.groups,
+                |list| {
+                    // Groups without members are omitted to keep report short.
+                    list.iter()
+                        .filter(|g| !g.members.is_empty() || !g.admins.is_empty())
+                        .map(|g| {
+                            vec![
+                                Cell::new(&g.name),
+                                Cell::new(&g.gid),
+                                Cell::new(&g.members.join(", ")),
+                                Cell::new(&g.admins.join(", ")),
+                            ]
+                        })
+                        .collect()
+                },
+            ),
+            table(
+                "Users",
+                &[
+                    "Name", "UID", "GID", "Home", "Shell", "Password", "Expires", "Groups",
+                ],
+                &report.computer.users,
+                |list| {
+                    // System accounts without a login shell are omitted.
+                    list.iter()
+                        .filter(|u| u.has_login_shell())
+                        .map(|u| {
+                            let (password, expires) = u.shadow.as_ref().map_or_else(
+                                || (String::new(), String::new()),
+                                |s| {
+                                    let expires = match s.password_expire() {
+                                        Some(time) => format_time(time),
+                                        None if s.password_expires() => String::new(),
+                                        None => "never".to_owned(),
+                                    };
+                                    (format!("{:?}", s.password), expires)
+                                },
+                            );
+                            vec![
+                                Cell::new(&u.name),
+                                Cell::new(&u.uid),
+                                Cell::new(&u.gid),
+                                Cell::new(&u.home),
+                                Cell::new(&u.shell),
+                                Cell::new(&password),
+                                Cell::new(&expires),
+                                Cell::new(&u.groups.join(", ")),
+                            ]
+                        })
+                        .collect()
+                },
+            ),
+            table(
+                "Locales",
+                &["Locale", "Language", "Territory", "Code Set", "Revision"],
+                &report.computer.languages,
+                |list| {
+                    list.iter()
+                        .map(|l| {
+                            vec![
+                                Cell::new(&l.locale),
+                                Cell::new(&l.language),
+                                Cell::new(&l.territory),
+                                Cell::new(&l.code_set),
+                                Cell::new(&l.revision),
+                            ]
+                        })
+                        .collect()
+                },
+            ),
+            table(
+                "Boot History",
+                &["Kernel", "Boot", "End", "Duration"],
+                &report.computer.bootup,
+                |list| {
+                    list.iter()
+                        .map(|b| {
+                            let end = match (b.end, b.end_time) {
+                                (BootEnd::Shutdown, Some(time)) => format_time(time),
+                                (BootEnd::Crash, _) => "crash".to_owned(),
+                                _ => "still running".to_owned(),
+                            };
+                            vec![
+                                Cell::new(&b.kernel),
+                                Cell::new(&format_time(b.start_time)),
+                                Cell::new(&end),
+                                Cell::new(&b.duration().map(format_duration).unwrap_or_default()),
+                            ]
+                        })
+                        .collect()
+                },
+            ),
+            table(
+                "Kernel Modules",
+                &["Name", "Size", "Used by", "Options", "Blacklisted"],
+                &report.computer.modules,
+                |list| {
+                    list.iter()
+                        .map(|m| {
+                            vec![
+                                Cell::new(&m.name),
+                                Cell::new(&m.memory_bytes),
+                                Cell::new(&m.dependencies.join(", ")),
+                                Cell::new(&m.configured_options.join(" ")),
+                                Cell::new(if m.blacklisted { "yes" } else { "" }),
+                            ]
+                        })
+                        .collect()
+                },
+            ),
+        ],
+    };
+
+    let device = Chapter {
+        title: "Devices",
+        tables: vec![
+            table(
+                "Batteries",
+                &[
+                    "Name",
+                    "Manufacturer",
+                    "Model",
+                    "Technology",
+                    "Status",
+                    "Capacity",
+                    "Full charge",
+                    "Design charge",
+                    "Cycles",
+                ],
+                &report.device.power_supply,
+                |list| {
+                    list.iter()
+                        .map(|b| {
+                            vec![
+                                Cell::new(&b.name),
+                                Cell::vendor(&b.manufacturer),
+                                Cell::new(&b.model_name),
+                                Cell::new(&format!("{:?}", b.technology)),
+                                Cell::new(&format!("{:?}", b.status)),
+                                Cell::new(&format!("{}%", b.capacity)),
+                                Cell::new(&b.charge_full),
+                                Cell::new(&b.charge_full_design),
+                                Cell::new(&b.cycle_count),
+                            ]
+                        })
+                        .collect()
+                },
+            ),
+            table(
+                "USB Devices",
+                &["Bus", "Device", "ID", "Vendor", "Product", "Speed"],
+                &report.device.usb,
+                |list| {
+                    list.iter()
+                        .map(|d| {
+                            let vendor = if d.vendor_from_database.is_empty() {
+                                &d.manufacturer
+                            } else {
+                                &d.vendor_from_database
+                            };
+                            let product = if d.model_from_database.is_empty() {
+                                &d.product
+                            } else {
+                                &d.model_from_database
+                            };
+                            vec![
+                                Cell::new(&d.bus_num),
+                                Cell::new(&d.dev_num),
+                                Cell::new(&format!("{}:{}", d.vendor_id, d.model_id)),
+                                Cell::vendor(vendor),
+                                Cell::new(&product),
+                                Cell::new(&d.speed),
+                            ]
+                        })
+                        .collect()
+                },
+            ),
+        ],
+    };
+
+    let network = Chapter {
+        title: "Network",
+        tables: vec![
+            table(
+                "Interfaces",
+                &["Interface", "Type", "MAC", "MTU", "RX bytes", "TX bytes"],
+                &report.network.dev,
+                |list| {
+                    list.iter()
+                        .map(|d| {
+                            vec![
+                                Cell::new(&d.interface),
+                                Cell::new(&format!("{:?}", d.dev_type)),
+                                Cell::new(&d.mac),
+                                Cell::new(&d.mtu),
+                                Cell::new(&d.received_bytes),
+                                Cell::new(&d.transmit_bytes),
+                            ]
+                        })
+                        .collect()
+                },
+            ),
+            table(
+                "Routing Table",
+                &[
+                    "Destination",
+                    "Gateway",
+                    "Mask",
+                    "Flags",
+                    "Metric",
+                    "Interface",
+                ],
+                &report.network.routing_table,
+                |list| {
+                    list.iter()
+                        .map(|r| {
+                            vec![
+                                ip_cell(&r.destination),
+                                ip_cell(&r.gateway),
+                                ip_cell(&r.mask),
+                                Cell::new(&r.flags),
+                                Cell::new(&r.metric),
+                                Cell::new(&r.interface),
+                            ]
+                        })
+                        .collect()
+                },
+            ),
+            table(
+                "ARP Table",
+                &["IP", "HW address", "Flags", "Device"],
+                &report.network.arp_table,
+                |list| {
+                    list.iter()
+                        .map(|a| {
+                            vec![
+                                Cell::new(&a.ip),
+                                Cell::new(&a.hw_address),
+                                Cell::new(&a.flags),
+                                Cell::new(&a.device),
+                            ]
+                        })
+                        .collect()
+                },
+            ),
+            table(
+                "DNS Servers",
+                &["IP", "Hostname"],
+                &report.network.dns_server,
+                |list| {
+                    list.iter()
+                        .map(|d| vec![Cell::new(&d.ip), Cell::new(&d.hostname)])
+                        .collect()
+                },
+            ),
+        ],
+    };
+
+    vec![computer, device, network]
+}
@@ -0,0 +1,115 @@
+// Copyright (c) 2023 Xu Shaohua <shaohua@biofan.org>. All rights reserved.
+// Use of this source is governed by General Public License that can be found
+// in the LICENSE file.
+
+//! Render report as standalone html page.
+
+use std::fmt::Write;
+
+use super::document::{self, Cell, Content};
+use super::SystemReport;
+
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em; }
+h1 { border-bottom: 2px solid #444; }
+h2 { border-bottom: 1px solid #888; margin-top: 2em; }
+table { border-collapse: collapse; margin-bottom: 1em; }
+th, td { border: 1px solid #ccc; padding: 0.25em 0.75em; text-align: left; }
+th { background: #eee; }
+.error { color: #b00; }";
+
+#[must_use]
+pub fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn cell(cell: &Cell) -> String {
+    cell.link.map_or_else(
+        || escape(&cell.text),
+        |link| format!("<a href=\"{}\">{}</a>", escape(link), escape(&cell.text)),
+    )
+}
+
+/// Render report as html document.
+#[must_use]
+pub fn render(report: &SystemReport) -> String {
+    let mut html = String::new();
+    let _ = writeln!(html, "<!DOCTYPE html>");
+    let _ = writeln!(html, "<html>\n<head>\n<meta charset=\"utf-8\">");
+    let _ = writeln!(html, "<title>System Report</title>");
+    let _ = writeln!(html, "<style>\n{STYLE}\n</style>\n</head>\n<body>");
+    let _ = writeln!(html, "<h1>System Report</h1>");
+
+    for chapter in document::build(report) {
+        let _ = writeln!(html, "<h2>{}</h2>", escape(chapter.title));
+        for table in chapter.tables {
+            let _ = writeln!(html, "<h3>{}</h3>", escape(table.title));
+            match table.content {
+                Content::Table { headers, rows } => {
+                    let _ = writeln!(html, "<table>\n<tr>");
+                    for header in headers {
+                        let _ = writeln!(html, "<th>{}</th>", escape(header));
+                    }
+                    let _ = writeln!(html, "</tr>");
+                    for row in rows {
+                        let _ = write!(html, "<tr>");
+                        for item in &row {
+                            let _ = write!(html, "<td>{}</td>", cell(item));
+                        }
+                        let _ = writeln!(html, "</tr>");
+                    }
+                    let _ = writeln!(html, "</table>");
+                }
+                Content::Error(err) => {
+                    let _ = writeln!(html, "<p class=\"error\">Error: {}</p>", escape(&err));
+                }
+            }
+        }
+    }
+
+    let _ = writeln!(html, "</body>\n</html>");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape, render};
+    use crate::base::system_root::SystemRoot;
+    use crate::report::SystemReport;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape("<a href=\"x\">&</a>"),
+            "&lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let report = SystemReport::collect(&SystemRoot::fixture("laptop"));
+        let html = render(&report);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<h2>Devices</h2>"));
+        assert!(html.contains("<td><a href=\"https://www.logitech.com\">Logitech, Inc.</a></td>"));
+        assert!(html.contains("<td>192.168.1.1</td>"));
+        assert!(html.trim_end().ends_with("</html>"));
+    }
+
+    #[test]
+    fn test_render_error() {
+        let report = SystemReport::collect(&SystemRoot::fixture("partial"));
+        let html = render(&report);
+        assert!(html.contains("<h3>USB Devices</h3>\n<p class=\"error\">Error: "));
+    }
+}
@@ -0,0 +1,90 @@
+// Copyright (c) 2023 Xu Shaohua <shaohua@biofan.org>. All rights reserved.
+// Use of this source is governed by General Public License that can be found
+// in the LICENSE file.
+
+//! Render report as markdown, with GitHub flavored tables.
+
+use std::fmt::Write;
+
+use super::document::{self, Cell, Content};
+use super::SystemReport;
+
+#[must_use]
+pub fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '|' | '\\' | '*' | '_' | '`' | '[' | ']' | '<' | '>' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\n' => out.push(' '),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn cell(cell: &Cell) -> String {
+    cell.link.map_or_else(
+        || escape(&cell.text),
+        |link| format!("[{}]({link})", escape(&cell.text)),
+    )
+}
+
+/// Render report as markdown document.
+#[must_use]
+pub fn render(report: &SystemReport) -> String {
+    let mut md = String::new();
+    let _ = writeln!(md, "# System Report");
+
+    for chapter in document::build(report) {
+        let _ = writeln!(md, "\n## {}", chapter.title);
+        for table in chapter.tables {
+            let _ = writeln!(md, "\n### {}\n", table.title);
+            match table.content {
+                Content::Table { headers, rows } => {
+                    let _ = writeln!(md, "| {} |", headers.join(" | "));
+                    let _ = writeln!(md, "|{}", " --- |".repeat(headers.len()));
+                    for row in rows {
+                        let cells: Vec<String> = row.iter().map(cell).collect();
+                        let _ = writeln!(md, "| {} |", cells.join(" | "));
+                    }
+                }
+                Content::Error(err) => {
+                    let _ = writeln!(md, "**Error**: {}", escape(&err));
+                }
+            }
+        }
+    }
+
+    md
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape, render};
+    use crate::base::system_root::SystemRoot;
+    use crate::report::SystemReport;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("a|b_c"), "a\\|b\\_c");
+    }
+
+    #[test]
+    fn test_render() {
+        let report = SystemReport::collect(&SystemRoot::fixture("laptop"));
+        let md = render(&report);
+        assert!(md.starts_with("# System Report\n"));
+        assert!(md.contains("\n## Network\n"));
+        assert!(md.contains(
+            "| Name | Size | Used by | Options | Blacklisted |\n\
+             | --- | --- | --- | --- | --- |\n\
+             | stp | 16384 | bridge |  | yes |\n"
+        ));
+        assert!(
+            md.contains("| 001 | 002 | 046d:c52b | [Logitech, Inc.](https://www.logitech.com) |")
+        );
+    }
+}
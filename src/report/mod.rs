@@ -0,0 +1,219 @@
+// Copyright (c) 2023 Xu Shaohua <shaohua@biofan.org>. All rights reserved.
+// Use of this source is governed by General Public License that can be found
+// in the LICENSE file.
+
+//! Collect info of all subsystems in one report.
+//!
+//! Each section is collected independently, a failed collector is recorded
+//! as error of its own section and does not abort the others.
+
+use std::time::Duration;
+
+use crate::base::system_root::SystemRoot;
+use crate::computer::bootup::{self, Bootup};
+use crate::computer::failed_login::{self, Attempts};
+use crate::computer::group::{self, Group};
+use crate::computer::language::{self, Language};
+use crate::computer::module::{self, Module};
+use crate::computer::os::{self, OperatingSystem};
+use crate::computer::session::{self, Session};
+use crate::computer::uptime::{self, UptimeInfo};
+use crate::computer::user::{self, User};
+use crate::device::power_supply::{self, PowerSupply};
+use crate::device::usb::{self, UsbDev};
+use crate::error::Error;
+use crate::network::arp_table::{self, Arp};
+use crate::network::dev::{self, Dev};
+use crate::network::dns_server::{self, DnsServer};
+use crate::network::routing_table::{self, Routing};
+
+pub mod diff;
+mod document;
+pub mod html;
+pub mod markdown;
+
+/// Collected data of a report section, or the error message if failed.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Section<T> {
+    Data(T),
+    Error(String),
+}
+
+impl<T> From<Result<T, Error>> for Section<T> {
+    fn from(result: Result<T, Error>) -> Self {
+        match result {
+            Ok(data) => Self::Data(data),
+            Err(err) => Self::Error(err.to_string()),
+        }
+    }
+}
+
+impl<T> Section<T> {
+    #[must_use]
+    pub const fn is_ok(&self) -> bool {
+        matches!(self, Self::Data(_))
+    }
+
+    #[must_use]
+    pub const fn data(&self) -> Option<&T> {
+        match self {
+            Self::Data(data) => Some(data),
+            Self::Error(_) => None,
+        }
+    }
+
+    #[must_use]
+    pub fn error(&self) -> Option<&str> {
+        match self {
+            Self::Data(_) => None,
+            Self::Error(err) => Some(err),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ComputerReport {
+    /// Distribution, kernel and host name.
+    pub os: Section<OperatingSystem>,
+    pub bootup: Section<Vec<Bootup>>,
+    pub modules: Section<Vec<Module>>,
+    pub uptime: Section<Duration>,
+
+    /// Idle time, boot time and load averages.
+    pub uptime_info: Section<UptimeInfo>,
+    pub sessions: Section<Vec<Session>>,
+
+    /// Failed login attempts grouped by source host.
+    pub failed_logins: Section<Vec<Attempts>>,
+    pub groups: Section<Vec<Group>>,
+    pub users: Section<Vec<User>>,
+
+    /// Generated locales.
+    pub languages: Section<Vec<Language>>,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceReport {
+    pub power_supply: Section<Vec<PowerSupply>>,
+    pub usb: Section<Vec<UsbDev>>,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NetworkReport {
+    pub dev: Section<Vec<Dev>>,
+    pub routing_table: Section<Vec<Routing>>,
+    pub arp_table: Section<Vec<Arp>>,
+    pub dns_server: Section<Vec<DnsServer>>,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SystemReport {
+    pub computer: ComputerReport,
+    pub device: DeviceReport,
+    pub network: NetworkReport,
+}
+
+impl SystemReport {
+    /// Run all collectors against `root`, best effort.
+    #[must_use]
+    pub fn collect(root: &SystemRoot) -> Self {
+        Self {
+            computer: ComputerReport {
+                os: os::get_os(root).into(),
+                bootup: bootup::get_list(root).into(),
+                modules: module::get_list(root).into(),
+                uptime: uptime::get_uptime(root).into(),
+                uptime_info: uptime::get_uptime_info(root).into(),
+                sessions: session::get_list(root).into(),
+                failed_logins: failed_login::get_list(root)
+                    .map(|list| failed_login::group_by_host(&list, ..))
+                    .into(),
+                groups: group::get_group_list(root).into(),
+                users: user::get_user_list(root).into(),
+                languages: language::get_list(root).into(),
+            },
+            device: DeviceReport {
+                power_supply: power_supply::get_list(root).into(),
+                usb: usb::scan_usb(root).into(),
+            },
+            network: NetworkReport {
+                dev: dev::get_list(root).into(),
+                routing_table: routing_table::get_list(root).into(),
+                arp_table: arp_table::get_list(root).into(),
+                dns_server: dns_server::get_list(root).into(),
+            },
+        }
+    }
+
+    /// Get name and error message of failed sections.
+    #[must_use]
+    pub fn errors(&self) -> Vec<(&'static str, &str)> {
+        let sections = [
+            ("os", self.computer.os.error()),
+            ("bootup", self.computer.bootup.error()),
+            ("modules", self.computer.modules.error()),
+            ("uptime", self.computer.uptime.error()),
+            ("uptime_info", self.computer.uptime_info.error()),
+            ("sessions", self.computer.sessions.error()),
+            ("failed_logins", self.computer.failed_logins.error()),
+            ("groups", self.computer.groups.error()),
+            ("users", self.computer.users.error()),
+            ("languages", self.computer.languages.error()),
+            ("power_supply", self.device.power_supply.error()),
+            ("usb", self.device.usb.error()),
+            ("dev", self.network.dev.error()),
+            ("routing_table", self.network.routing_table.error()),
+            ("arp_table", self.network.arp_table.error()),
+            ("dns_server", self.network.dns_server.error()),
+        ];
+        sections
+            .into_iter()
+            .filter_map(|(name, err)| err.map(|err| (name, err)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SystemReport;
+    use crate::base::system_root::SystemRoot;
+
+    #[test]
+    fn test_collect() {
+        let report = SystemReport::collect(&SystemRoot::fixture("laptop"));
+        assert_eq!(report.computer.modules.data().map(Vec::len), Some(4));
+        assert!(report.computer.uptime.is_ok());
+        assert_eq!(
+            report.computer.os.data().map(|os| os.hostname.as_str()),
+            Some("laptop")
+        );
+        assert_eq!(report.device.power_supply.data().map(Vec::len), Some(1));
+        assert_eq!(report.device.usb.data().map(Vec::len), Some(2));
+        assert_eq!(report.network.dev.data().map(Vec::len), Some(2));
+        assert!(report.network.routing_table.is_ok());
+        assert!(report.network.dns_server.is_ok());
+        assert_eq!(report.network.arp_table.data().map(Vec::len), Some(2));
+        assert!(report.errors().is_empty());
+    }
+
+    #[test]
+    fn test_collect_partial() {
+        // Only files of computer section exist.
+        let report = SystemReport::collect(&SystemRoot::fixture("partial"));
+        assert!(report.computer.modules.is_ok());
+        assert!(report.computer.uptime.is_ok());
+        assert!(report.computer.os.is_ok());
+        assert!(!report.device.usb.is_ok());
+        assert!(!report.network.dns_server.is_ok());
+
+        let errors = report.errors();
+        assert_eq!(errors.len(), 6);
+        assert_eq!(errors[0].0, "power_supply");
+    }
+}
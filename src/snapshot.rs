@@ -0,0 +1,326 @@
+// Copyright (c) 2023 Xu Shaohua <shaohua@biofan.org>. All rights reserved.
+// Use of this source is governed by General Public License that can be found
+// in the LICENSE file.
+
+//! Capture files read by collectors into a single archive, and replay them later.
+//!
+//! A snapshot is a tar archive which holds the files in procfs, sysfs and `/etc`
+//! exactly as collectors read them, except files holding password hashes.
+//! Unpack it to a directory and use that directory as `SystemRoot` to reproduce
+//! parsing failures on another machine.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::base::system_root::SystemRoot;
+use crate::computer::{
+    bootup, failed_login, group, language, module, module_graph, os, session, uptime, user,
+};
+use crate::device::{power_supply, usb};
+use crate::error::Error;
+use crate::network::{arp_table, dev, dns_server, routing_table};
+
+/// Files which hold password hashes, they are never recorded.
+const SECRET_FILES: &[&str] = &["/etc/gshadow", "/etc/shadow"];
+
+/// File added to every snapshot, so that a replayed root can be told apart.
+pub const MARKER_FILE: &str = "/.xingtian-snapshot";
+
+/// Files and directories recorded from target system.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Snapshot {
+    files: BTreeMap<PathBuf, Vec<u8>>,
+    dirs: BTreeSet<PathBuf>,
+}
+
+/// Shared handle which records files read through `SystemRoot`.
+#[derive(Debug, Clone, Default)]
+pub struct Recorder {
+    snapshot: Arc<Mutex<Snapshot>>,
+}
+
+impl Recorder {
+    pub(crate) fn add_file(&self, path: &Path, content: &[u8]) {
+        let path = absolute_path(path);
+        if SECRET_FILES.iter().any(|secret| path == Path::new(secret)) {
+            log::info!("Skip secret file in snapshot: {}", path.display());
+            return;
+        }
+        if let Ok(mut snapshot) = self.snapshot.lock() {
+            snapshot.files.insert(path, content.to_vec());
+        }
+    }
+
+    pub(crate) fn add_dir(&self, path: &Path) {
+        if let Ok(mut snapshot) = self.snapshot.lock() {
+            snapshot.dirs.insert(absolute_path(path));
+        }
+    }
+
+    /// Get a copy of files recorded so far.
+    #[must_use]
+    pub fn snapshot(&self) -> Snapshot {
+        self.snapshot
+            .lock()
+            .map(|snapshot| snapshot.clone())
+            .unwrap_or_default()
+    }
+}
+
+fn absolute_path(path: &Path) -> PathBuf {
+    Path::new("/").join(path)
+}
+
+fn log_error<T>(name: &str, result: Result<T, Error>) {
+    if let Err(err) = result {
+        log::warn!("Failed to capture {name}: {err}");
+    }
+}
+
+impl Snapshot {
+    /// Run all collectors against `root` and record every file they read.
+    ///
+    /// Errors of collectors are logged and ignored, so that the files which
+    /// failed to parse are still kept in snapshot.
+    #[must_use]
+    pub fn capture(root: &SystemRoot) -> Self {
+        let recorder = Recorder::default();
+        let root = root.clone().with_recorder(recorder.clone());
+
+        log_error("bootup", bootup::get_list(&root));
+        log_error("failed login", failed_login::get_list(&root));
+        log_error("group", group::get_group_list(&root));
+        log_error("user", user::get_user_list(&root));
+        log_error("language", language::get_list(&root));
+        log_error("modules", module::get_list(&root));
+        log_error("builtin modules", module::get_builtin_list(&root));
+        log_error("module graph", module_graph::get_graph(&root));
+        log_error("os", os::get_os(&root));
+        log_error("session", session::get_list(&root));
+        log_error("uptime", uptime::get_uptime_info(&root));
+        log_error("power supply", power_supply::get_list(&root));
+        log_error("usb", usb::scan_usb(&root));
+        log_error("arp table", arp_table::get_list(&root));
+        log_error("network dev", dev::get_list(&root));
+        log_error("dns server", dns_server::get_list(&root));
+        log_error("routing table", routing_table::get_list(&root));
+
+        recorder.add_file(Path::new(MARKER_FILE), b"");
+        recorder.snapshot()
+    }
+
+    /// Get recorded files, with absolute path in target system as key.
+    #[must_use]
+    pub const fn files(&self) -> &BTreeMap<PathBuf, Vec<u8>> {
+        &self.files
+    }
+
+    /// Get recorded directories.
+    #[must_use]
+    pub const fn dirs(&self) -> &BTreeSet<PathBuf> {
+        &self.dirs
+    }
+
+    /// Write snapshot as tar archive.
+    ///
+    /// # Errors
+    /// Returns error if failed to write archive.
+    pub fn write_to<W: Write>(&self, writer: W) -> Result<(), Error> {
+        let to_error = Error::Archive;
+        let mut builder = tar::Builder::new(writer);
+
+        for dir in &self.dirs {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Directory);
+            header.set_mode(0o755);
+            header.set_size(0);
+            builder
+                .append_data(&mut header, relative_path(dir), io::empty())
+                .map_err(to_error)?;
+        }
+        for (path, content) in &self.files {
+            let mut header = tar::Header::new_gnu();
+            header.set_mode(0o644);
+            header.set_size(content.len() as u64);
+            builder
+                .append_data(&mut header, relative_path(path), content.as_slice())
+                .map_err(to_error)?;
+        }
+
+        builder
+            .into_inner()
+            .map_err(to_error)?
+            .flush()
+            .map_err(to_error)
+    }
+
+    /// Save snapshot as tar archive to `path`.
+    ///
+    /// # Errors
+    /// Returns error if failed to write archive file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        let file = File::create(path).map_err(|err| Error::from_io(path, err))?;
+        self.write_to(file)
+    }
+
+    /// Read snapshot from tar archive.
+    ///
+    /// # Errors
+    /// Returns error if failed to read archive or archive contains invalid path.
+    pub fn read_from<R: Read>(reader: R) -> Result<Self, Error> {
+        let to_error = Error::Archive;
+        let mut archive = tar::Archive::new(reader);
+        let mut snapshot = Self::default();
+
+        for entry in archive.entries().map_err(to_error)? {
+            let mut entry = entry.map_err(to_error)?;
+            let path = entry.path().map_err(to_error)?;
+            if !path
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)))
+            {
+                return Err(Error::malformed(
+                    &path,
+                    None,
+                    "path",
+                    &path.display().to_string(),
+                ));
+            }
+            let path = absolute_path(&path);
+
+            match entry.header().entry_type() {
+                tar::EntryType::Directory => {
+                    snapshot.dirs.insert(path);
+                }
+                tar::EntryType::Regular => {
+                    let mut content = Vec::new();
+                    entry.read_to_end(&mut content).map_err(to_error)?;
+                    snapshot.files.insert(path, content);
+                }
+                entry_type => {
+                    log::warn!(
+                        "Ignore {entry_type:?} entry in snapshot: {}",
+                        path.display()
+                    );
+                }
+            }
+        }
+
+        Ok(snapshot)
+    }
+
+    /// Load snapshot from tar archive at `path`.
+    ///
+    /// # Errors
+    /// Returns error if failed to read archive file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|err| Error::from_io(path, err))?;
+        Self::read_from(file)
+    }
+
+    /// Write recorded files to `dir` and returns a `SystemRoot` to replay them.
+    ///
+    /// # Errors
+    /// Returns error if failed to create files in `dir`.
+    pub fn unpack<P: AsRef<Path>>(&self, dir: P) -> Result<SystemRoot, Error> {
+        let root = SystemRoot::new(dir.as_ref());
+        let to_error = |path: &Path, err| Error::from_io(path, err);
+
+        for dir in &self.dirs {
+            let real_path = root.join(dir);
+            fs::create_dir_all(&real_path).map_err(|err| to_error(&real_path, err))?;
+        }
+        for (path, content) in &self.files {
+            let real_path = root.join(path);
+            if let Some(parent) = real_path.parent() {
+                fs::create_dir_all(parent).map_err(|err| to_error(parent, err))?;
+            }
+            fs::write(&real_path, content).map_err(|err| to_error(&real_path, err))?;
+        }
+
+        Ok(root)
+    }
+}
+
+fn relative_path(path: &Path) -> &Path {
+    path.strip_prefix("/").unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::path::Path;
+    use std::process;
+
+    use super::Snapshot;
+    use crate::base::system_root::SystemRoot;
+    use crate::computer::{module, module_graph, os, session};
+    use crate::device::power_supply;
+
+    #[test]
+    fn test_capture() {
+        let snapshot = Snapshot::capture(&SystemRoot::fixture("laptop"));
+        let files = snapshot.files();
+        assert!(files.contains_key(Path::new("/proc/modules")));
+        assert!(files.contains_key(Path::new("/proc/net/dev")));
+        assert!(files.contains_key(Path::new("/sys/class/net/wlp2s0/mtu")));
+        assert!(files.contains_key(Path::new("/sys/class/power_supply/BAT0/uevent")));
+        assert!(files.contains_key(Path::new("/sys/bus/usb/devices/1-1/uevent")));
+        assert!(files.contains_key(Path::new("/run/udev/data/c189:1")));
+        assert!(files.contains_key(Path::new("/etc/group")));
+        assert!(files.contains_key(Path::new("/proc/sys/kernel/version")));
+        // Secret files are not recorded.
+        assert!(!files.contains_key(Path::new("/etc/gshadow")));
+        assert!(files.contains_key(Path::new("/etc/passwd")));
+        assert!(!files.contains_key(Path::new("/etc/shadow")));
+        // Not read by any collector.
+        assert!(!files.contains_key(Path::new("/sys/class/power_supply/AC/uevent")));
+        assert!(snapshot
+            .dirs()
+            .contains(Path::new("/sys/class/power_supply")));
+    }
+
+    #[test]
+    fn test_replay() {
+        let snapshot = Snapshot::capture(&SystemRoot::fixture("laptop"));
+        let mut archive = Vec::new();
+        assert!(snapshot.write_to(&mut archive).is_ok());
+
+        let loaded = Snapshot::read_from(archive.as_slice());
+        assert!(loaded.is_ok());
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded, snapshot);
+
+        let dir = env::temp_dir().join(format!("xingtian-snapshot-{}", process::id()));
+        let root = loaded.unpack(&dir);
+        assert!(root.is_ok());
+        let root = root.unwrap();
+
+        let modules = module::get_list(&root);
+        assert!(modules.is_ok());
+        assert_eq!(modules.unwrap().len(), 4);
+        let graph = module_graph::get_graph(&root);
+        assert!(graph.is_ok());
+        assert_eq!(graph.unwrap().dependencies_of("snd_hda_intel").len(), 5);
+        let os = os::get_os(&root);
+        assert!(os.is_ok());
+        assert_eq!(os.unwrap().kernel.release, "6.5.0-2-amd64");
+        let batteries = power_supply::get_list(&root);
+        assert!(batteries.is_ok());
+        assert_eq!(batteries.unwrap()[0].name, "BAT0");
+
+        // Access time of tty is not recorded.
+        assert!(root.is_snapshot());
+        let sessions = session::get_list(&root);
+        assert!(sessions.is_ok());
+        assert!(sessions.unwrap().iter().all(|s| s.idle.is_none()));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}